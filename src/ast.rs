@@ -16,21 +16,28 @@ pub enum AstNode {
 	Loop(Ast),
 	FunctionCall(String, Vec<Expression>),
 	FunctionDeclaration(String, Vec<Param>, Option<DataType>, Ast),
+	Test(String, Ast),
 	Break,
 	Return(Expression),
 	Exit(Expression),
+	Error,
 }
 
 #[derive(Clone, Debug)]
 pub enum Expression {
 	BinaryOperation(Box<Expression>, Token, Box<Expression>),
+	/// `-x` or `!x`, binding tighter than every operator except `^`.
+	UnaryOperation(Token, Box<Expression>),
 	Str(String),
 	Int(i32),
+	Float(f64),
 	Bool(bool),
 	FunctionCall(String, Vec<Expression>),
 	Identifier(String),
 	Vector(Vec<Expression>, Option<DataType>),
 	VecIndex(String, Box<Expression>),
+	VecSlice(String, Box<Expression>, Box<Expression>),
+	Block(Ast, Box<Expression>),
 }
 
 impl Expression {
@@ -52,18 +59,25 @@ impl Expression {
 					(_, DataType::Vector(t)) => DataType::Vector(t),
 					(DataType::Str, _) => DataType::Str,
 					(_, DataType::Str) => DataType::Str,
+					(DataType::Float, _) => DataType::Float,
+					(_, DataType::Float) => DataType::Float,
 					(DataType::Int, _) => DataType::Int,
 					(_, DataType::Int) => DataType::Int,
 					(DataType::Bool, _) => DataType::Bool,
 				})
 			}
+			Expression::UnaryOperation(Token::Not, _) => Some(DataType::Bool),
+			Expression::UnaryOperation(_, expression) => expression.infer_datatype(),
 			Expression::Str(_) => Some(DataType::Str),
 			Expression::Int(_) => Some(DataType::Int),
+			Expression::Float(_) => Some(DataType::Float),
 			Expression::Bool(_) => Some(DataType::Bool),
 			Expression::FunctionCall(_, _) => None,
 			Expression::Identifier(_) => None,
 			Expression::Vector(_, d) => d.clone(),
 			Expression::VecIndex(_, _) => None,
+			Expression::VecSlice(_, _, _) => None,
+			Expression::Block(_, expression) => expression.infer_datatype(),
 		}
 	}
 }
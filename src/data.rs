@@ -1,10 +1,14 @@
 use crate::ast::Ast;
-use std::fmt::{self, Display};
+use std::{
+	cmp::Ordering,
+	fmt::{self, Display},
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone)]
 pub enum Data {
 	Str(String),
 	Int(i32),
+	Float(f64),
 	Bool(bool),
 	Vector(Vec<Data>, DataType),
 }
@@ -14,10 +18,93 @@ impl Data {
 		match self {
 			Data::Str(_) => DataType::Str,
 			Data::Int(_) => DataType::Int,
+			Data::Float(_) => DataType::Float,
 			Data::Bool(_) => DataType::Bool,
 			Data::Vector(_, t) => DataType::Vector(Box::new(t.clone())),
 		}
 	}
+
+	/// Bytes the value takes up, approximately.
+	pub fn size(&self) -> usize {
+		std::mem::size_of::<Data>()
+			+ match self {
+				Data::Str(s) => s.len(),
+				Data::Vector(vec, _) => vec.iter().map(Data::size).sum(),
+				_ => 0,
+			}
+	}
+
+	/// Formats the value the way `print` writes it.
+	pub fn as_string(&self) -> String {
+		match self {
+			Data::Str(s) => s.clone(),
+			Data::Int(i) => i.to_string(),
+			Data::Float(f) => float(*f),
+			Data::Bool(b) => b.to_string(),
+			Data::Vector(vec, _) => {
+				let items = vec.iter().map(Data::as_string).collect::<Vec<_>>();
+				"[".to_owned() + &items.join(", ") + "]"
+			}
+		}
+	}
+}
+
+/// Formats a float so that it reads back as one, `2` is written as `2.0`.
+pub fn float(f: f64) -> String {
+	let s = f.to_string();
+
+	if f.is_finite() && !s.contains('.') {
+		s + ".0"
+	} else {
+		s
+	}
+}
+
+impl Data {
+	/// Orders values of the same type, floats by `total_cmp` so that vectors
+	/// of them can be sorted even with `NaN` in them.
+	pub fn total_cmp(&self, other: &Self) -> Ordering {
+		match (self, other) {
+			(Data::Str(a), Data::Str(b)) => a.cmp(b),
+			(Data::Int(a), Data::Int(b)) => a.cmp(b),
+			(Data::Float(a), Data::Float(b)) => a.total_cmp(b),
+			(Data::Bool(a), Data::Bool(b)) => a.cmp(b),
+			(Data::Vector(a, t), Data::Vector(b, u)) => a
+				.iter()
+				.zip(b)
+				.map(|(a, b)| a.total_cmp(b))
+				.find(|ordering| ordering.is_ne())
+				.unwrap_or_else(|| a.len().cmp(&b.len()))
+				.then_with(|| t.cmp(u)),
+			_ => self.r#type().cmp(&other.r#type()),
+		}
+	}
+}
+
+// floats are equal as in IEEE 754, so `0.0 == -0.0` and `NaN` equals nothing
+impl PartialEq for Data {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Data::Str(a), Data::Str(b)) => a == b,
+			(Data::Int(a), Data::Int(b)) => a == b,
+			(Data::Float(a), Data::Float(b)) => a == b,
+			(Data::Bool(a), Data::Bool(b)) => a == b,
+			(Data::Vector(a, t), Data::Vector(b, u)) => a == b && t == u,
+			_ => false,
+		}
+	}
+}
+
+impl PartialOrd for Data {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		match (self, other) {
+			(Data::Float(a), Data::Float(b)) => a.partial_cmp(b),
+			(Data::Vector(a, t), Data::Vector(b, u)) => a
+				.partial_cmp(b)
+				.map(|ordering| ordering.then_with(|| t.cmp(u))),
+			_ => Some(self.total_cmp(other)),
+		}
+	}
 }
 
 impl Display for Data {
@@ -31,6 +118,7 @@ pub enum DataType {
 	Vector(Box<DataType>),
 	Str,
 	Int,
+	Float,
 	Bool,
 }
 
@@ -40,6 +128,7 @@ impl Display for DataType {
 			match d {
 				DataType::Str => String::from("str"),
 				DataType::Int => String::from("int"),
+				DataType::Float => String::from("float"),
 				DataType::Bool => String::from("bool"),
 				DataType::Vector(t) => "vec<".to_owned() + &match_type(t) + ">",
 			}
@@ -48,6 +137,127 @@ impl Display for DataType {
 	}
 }
 
+/// Rust types which convert to and from [`Data`], for the arguments and
+/// return values of functions registered by the program embedding Oxido.
+/// Failures are messages which can be returned from such a function.
+pub trait Value: Sized {
+	fn datatype() -> DataType;
+
+	fn from_data(data: Data) -> Result<Self, String>;
+
+	fn into_data(self) -> Result<Data, String>;
+}
+
+fn expected<T: Value>(data: &Data) -> String {
+	format!("expected `{}` found {data}", T::datatype())
+}
+
+impl Value for String {
+	fn datatype() -> DataType {
+		DataType::Str
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		match data {
+			Data::Str(s) => Ok(s),
+			data => Err(expected::<Self>(&data)),
+		}
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		Ok(Data::Str(self))
+	}
+}
+
+impl Value for i32 {
+	fn datatype() -> DataType {
+		DataType::Int
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		match data {
+			Data::Int(i) => Ok(i),
+			data => Err(expected::<Self>(&data)),
+		}
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		Ok(Data::Int(self))
+	}
+}
+
+impl Value for i64 {
+	fn datatype() -> DataType {
+		DataType::Int
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		i32::from_data(data).map(i64::from)
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		match i32::try_from(self) {
+			Ok(i) => Ok(Data::Int(i)),
+			Err(_) => Err(format!("`{self}` does not fit in an `int`")),
+		}
+	}
+}
+
+impl Value for f64 {
+	fn datatype() -> DataType {
+		DataType::Float
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		match data {
+			Data::Float(f) => Ok(f),
+			data => Err(expected::<Self>(&data)),
+		}
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		Ok(Data::Float(self))
+	}
+}
+
+impl Value for bool {
+	fn datatype() -> DataType {
+		DataType::Bool
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		match data {
+			Data::Bool(b) => Ok(b),
+			data => Err(expected::<Self>(&data)),
+		}
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		Ok(Data::Bool(self))
+	}
+}
+
+impl<T: Value> Value for Vec<T> {
+	fn datatype() -> DataType {
+		DataType::Vector(Box::new(T::datatype()))
+	}
+
+	fn from_data(data: Data) -> Result<Self, String> {
+		match data {
+			Data::Vector(items, _) => items.into_iter().map(T::from_data).collect(),
+			data => Err(expected::<Self>(&data)),
+		}
+	}
+
+	fn into_data(self) -> Result<Data, String> {
+		let items = self
+			.into_iter()
+			.map(T::into_data)
+			.collect::<Result<_, _>>()?;
+		Ok(Data::Vector(items, T::datatype()))
+	}
+}
+
 #[derive(Clone, Debug)]
 pub struct Variable {
 	pub datatype: DataType,
@@ -95,3 +305,19 @@ impl Function {
 		}
 	}
 }
+
+impl Display for Function {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let params = self
+			.params
+			.iter()
+			.map(|param| format!("{}: {}", param.name, param.datatype))
+			.collect::<Vec<_>>();
+
+		write!(f, "fn {}({})", self.name, params.join(", "))?;
+		if let Some(datatype) = &self.datatype {
+			write!(f, " -> {datatype}")?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,403 @@
+use std::{
+	cell::RefCell,
+	collections::BTreeSet,
+	fmt::{self, Debug},
+	io::{stdin, stdout, BufRead, Write},
+	ops::Range,
+	process::exit,
+	rc::Rc,
+};
+
+use crate::{
+	error,
+	interpreter::{Hook, Interpreter},
+	lexer::Lexer,
+	parser::Parser,
+	sandbox::Cancel,
+};
+
+const HELP: &str = "\
+break <line>    pause before the statements on a line, `b` for short
+delete <line>   remove the breakpoint on a line, `d` for short
+step            run until the next statement, `s` for short
+next            run until the next statement in this function, `n` for short
+continue        run until the next breakpoint, `c` for short
+print <name>    print a variable, `p` for short
+vars            print every variable
+stack           print the call stack, `bt` for short
+list            print the code around the current line, `l` for short
+quit            stop the program, `q` for short
+help            print this message, `h` for short";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+	Step,
+	/// Run until a statement at this call stack depth or shallower.
+	Next(usize),
+	Continue,
+}
+
+/// An interactive debugger, run before every statement by the interpreter.
+struct Debugger {
+	file: String,
+	breakpoints: BTreeSet<usize>,
+	mode: Mode,
+	/// Line and start of the last statement run, a breakpoint does not pause
+	/// again on statements after it on the same line unless control went
+	/// back, as it does in a loop.
+	last: Option<(usize, usize)>,
+	/// Where commands are read from, stdin if `None`. Stdin is only locked
+	/// while a command is read so that the program can read it too.
+	input: Option<Box<dyn BufRead>>,
+	output: Rc<RefCell<dyn Write>>,
+	/// Stops the program when the user quits.
+	cancel: Cancel,
+	quit: bool,
+}
+
+impl Debug for Debugger {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Debugger")
+			.field("breakpoints", &self.breakpoints)
+			.field("mode", &self.mode)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Runs `contents` under the debugger, pausing before the first statement.
+pub fn run(name: &str, contents: String) {
+	let tokens = match Lexer::new(&contents).run() {
+		Ok(tokens) => tokens.to_vec(),
+		Err(e) => {
+			error::report(name, &contents, &[e]);
+			exit(1);
+		}
+	};
+
+	let (ast, errors) = Parser::new().run(tokens);
+	if !errors.is_empty() {
+		error::report(name, &contents, &errors);
+		exit(1);
+	}
+
+	println!("debugging {name}, type `help` for a list of commands");
+
+	let debugger = Debugger::new(contents.clone(), None, Rc::new(RefCell::new(stdout())));
+	let debugger = Rc::new(RefCell::new(debugger));
+
+	let mut interpreter = Interpreter::new();
+	interpreter.set_cancel(debugger.borrow().cancel.clone());
+	interpreter.set_hook(debugger.clone());
+
+	match interpreter.run(ast) {
+		_ if debugger.borrow().quit => exit(0),
+		Ok(()) if interpreter.exited() => {
+			println!("program exited with status {}", interpreter.exit_code());
+			exit(interpreter.exit_code());
+		}
+		Ok(()) => println!("program finished"),
+		Err(e) => {
+			error::report(name, &contents, &[e]);
+			println!("program stopped with an error");
+			exit(1);
+		}
+	}
+}
+
+impl Hook for Debugger {
+	fn statement(&mut self, interpreter: &Interpreter, range: &Range<usize>) {
+		let line = self.line(range.start);
+		let depth = interpreter.stack().len();
+
+		let pause = match self.mode {
+			Mode::Step => true,
+			Mode::Next(at) => depth <= at,
+			Mode::Continue => false,
+		};
+		let after = matches!(self.last, Some((last, start)) if last == line && start < range.start);
+		let breakpoint = self.breakpoints.contains(&line) && !after;
+		self.last = Some((line, range.start));
+
+		if pause || breakpoint {
+			self.show(line);
+			self.prompt(interpreter, line, depth);
+		}
+	}
+}
+
+impl Debugger {
+	/// Creates a debugger for `file` reading commands from `input`, which
+	/// pauses before the first statement.
+	fn new(file: String, input: Option<Box<dyn BufRead>>, output: Rc<RefCell<dyn Write>>) -> Self {
+		Self {
+			file,
+			breakpoints: BTreeSet::new(),
+			mode: Mode::Step,
+			last: None,
+			input,
+			output,
+			cancel: Cancel::default(),
+			quit: false,
+		}
+	}
+
+	fn print(&self, text: &str) {
+		let mut output = self.output.borrow_mut();
+		let _ = output
+			.write_all(text.as_bytes())
+			.and_then(|()| output.flush());
+	}
+
+	fn println(&self, text: String) {
+		self.print(&(text + "\n"));
+	}
+
+	/// Stops the program before the statement it is paused at.
+	fn quit(&mut self) {
+		self.quit = true;
+		self.cancel.cancel();
+	}
+
+	/// The 1-based line `offset` is on.
+	fn line(&self, offset: usize) -> usize {
+		self.file[..offset].matches('\n').count() + 1
+	}
+
+	fn show(&self, line: usize) {
+		let text = self.file.lines().nth(line - 1).unwrap_or_default();
+		self.println(format!("\x1b[1m\x1b[32m-->\x1b[0m {line:>4} │ {text}"));
+	}
+
+	fn prompt(&mut self, interpreter: &Interpreter, line: usize, depth: usize) {
+		loop {
+			self.print("\x1b[1m\x1b[33m(debug)\x1b[0m ");
+
+			let mut input = String::new();
+			let read = match &mut self.input {
+				Some(commands) => commands.read_line(&mut input),
+				None => stdin().read_line(&mut input),
+			};
+			if read.unwrap_or(0) == 0 {
+				return self.quit();
+			}
+
+			let mut words = input.split_whitespace();
+			let command = words.next().unwrap_or("step");
+			let argument = words.next();
+
+			match command {
+				"s" | "step" => {
+					self.mode = Mode::Step;
+					return;
+				}
+				"n" | "next" => {
+					self.mode = Mode::Next(depth);
+					return;
+				}
+				"c" | "continue" => {
+					self.mode = Mode::Continue;
+					return;
+				}
+				"b" | "break" | "d" | "delete" => {
+					let line = match argument.map(str::parse::<usize>) {
+						Some(Ok(line)) if line > 0 => line,
+						_ => {
+							self.println(format!("expected a line number such as `{command} 4`"));
+							continue;
+						}
+					};
+
+					if command.starts_with('b') {
+						self.breakpoints.insert(line);
+						self.println(format!("breakpoint set on line {line}"));
+					} else if self.breakpoints.remove(&line) {
+						self.println(format!("breakpoint on line {line} removed"));
+					} else {
+						self.println(format!("no breakpoint on line {line}"));
+					}
+				}
+				"p" | "print" => match argument.and_then(|name| interpreter.variables().get(name)) {
+					Some(variable) => self.println(format!(
+						"{} : {}",
+						variable.data.as_string(),
+						variable.datatype
+					)),
+					None => self.println(format!(
+						"no variable called `{}`",
+						argument.unwrap_or_default()
+					)),
+				},
+				"vars" => {
+					let mut variables = interpreter.variables().iter().collect::<Vec<_>>();
+					variables.sort_by_key(|(name, _)| *name);
+
+					for (name, variable) in variables {
+						self.println(format!(
+							"{name}: {} = {}",
+							variable.datatype,
+							variable.data.as_string()
+						));
+					}
+				}
+				"bt" | "stack" => {
+					self.println(format!("#0 line {line}"));
+					for (i, (function, call)) in interpreter.stack().iter().rev().enumerate() {
+						self.println(format!(
+							"#{} in `{function}` called from line {}",
+							i + 1,
+							self.line(call.start)
+						));
+					}
+				}
+				"l" | "list" => {
+					let start = line.saturating_sub(3).max(1);
+					for (i, text) in self.file.lines().enumerate().skip(start - 1).take(7) {
+						let marker = if i + 1 == line { "-->" } else { "   " };
+						let breakpoint = if self.breakpoints.contains(&(i + 1)) {
+							"*"
+						} else {
+							" "
+						};
+						self.println(format!("{marker}{breakpoint}{:>4} │ {text}", i + 1));
+					}
+				}
+				"q" | "quit" => return self.quit(),
+				"h" | "help" => self.println(HELP.to_string()),
+				_ => self.println(format!(
+					"unknown command `{command}`, type `help` for a list of commands"
+				)),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Debugger;
+	use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser};
+	use std::{
+		cell::RefCell,
+		io::{BufRead, Cursor},
+		rc::Rc,
+	};
+
+	fn debug(file: &str, commands: &str) -> (String, bool) {
+		debug_reading(file, commands, "")
+	}
+
+	/// Runs `file` in the debugger with `commands` as its input and `stdin`
+	/// as the program's, returns the output of both without colours and
+	/// whether the program finished.
+	fn debug_reading(file: &str, commands: &str, stdin: &str) -> (String, bool) {
+		let tokens = Lexer::new(file).run().unwrap().to_vec();
+		let (ast, errors) = Parser::new().run(tokens);
+		assert!(errors.is_empty(), "{errors:?}");
+
+		let output = Rc::new(RefCell::new(vec![]));
+		let input = Some(Box::new(Cursor::new(commands.to_string())) as Box<dyn BufRead>);
+		let debugger = Rc::new(RefCell::new(Debugger::new(
+			file.to_string(),
+			input,
+			output.clone(),
+		)));
+
+		let mut interpreter = Interpreter::new();
+		interpreter.set_cancel(debugger.borrow().cancel.clone());
+		interpreter.set_hook(debugger.clone());
+		interpreter.set_stdout(output.clone());
+		interpreter.set_stdin(Rc::new(RefCell::new(Cursor::new(stdin.to_string()))));
+		let finished = interpreter.run(ast).is_ok();
+
+		let output = String::from_utf8(output.take()).unwrap();
+		let mut plain = String::new();
+		let mut chars = output.chars();
+		while let Some(c) = chars.next() {
+			if c == '\x1b' {
+				chars.by_ref().find(|c| *c == 'm');
+			} else {
+				plain.push(c);
+			}
+		}
+
+		(plain, finished)
+	}
+
+	/// Lines paused at, in order.
+	fn paused(output: &str) -> Vec<&str> {
+		output
+			.lines()
+			.filter_map(|line| line.split_once("-->"))
+			.filter_map(|(_, line)| line.split('│').next())
+			.map(str::trim)
+			.collect()
+	}
+
+	const FILE: &str =
+		"let a: int = 1;\nfn f(x: int) -> int {\n\treturn x + 1;\n}\nlet b: int = f(a);\nprintln(b);\n";
+
+	#[test]
+	fn step() {
+		let (output, finished) = debug(FILE, "s\ns\ns\ns\ns\n");
+		assert!(finished);
+		assert_eq!(paused(&output), ["1", "2", "5", "3", "6"]);
+		assert!(output.ends_with("2\n"));
+	}
+
+	#[test]
+	fn next() {
+		let (output, finished) = debug(FILE, "n\nn\nn\nn\n");
+		assert!(finished);
+		assert_eq!(paused(&output), ["1", "2", "5", "6"]);
+	}
+
+	#[test]
+	fn breakpoints_and_variables() {
+		let (output, finished) = debug(FILE, "b 3\nc\np x\nbt\nd 3\nc\n");
+		assert!(finished);
+		assert_eq!(paused(&output), ["1", "3"]);
+		assert!(output.contains("breakpoint set on line 3"));
+		assert!(output.contains("1 : int"));
+		assert!(output.contains("#1 in `f` called from line 5"));
+		assert!(output.contains("breakpoint on line 3 removed"));
+	}
+
+	#[test]
+	fn quit() {
+		let (output, finished) = debug(FILE, "s\nq\n");
+		assert!(!finished);
+		assert_eq!(paused(&output), ["1", "2"]);
+		assert!(!output.ends_with("2\n"));
+
+		// the end of the input quits too
+		let (_, finished) = debug(FILE, "");
+		assert!(!finished);
+	}
+
+	#[test]
+	fn one_line_loops() {
+		let file = "let i: int = 0;\nloop { i = i + 1; if i == 3 { break; } }\nprintln(i);\n";
+		let (output, finished) = debug(file, "b 2\nc\np i\nc\np i\nc\np i\nc\n");
+
+		assert!(finished);
+		assert_eq!(paused(&output), ["1", "2", "2", "2"]);
+		let values = output
+			.lines()
+			.filter(|line| line.ends_with(": int"))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			values,
+			["(debug) 0 : int", "(debug) 1 : int", "(debug) 2 : int"]
+		);
+		assert!(output.ends_with("3\n"));
+	}
+
+	#[test]
+	fn programs_reading_stdin() {
+		let file = "let a: str = read();\nprintln(a);\n";
+		let (output, finished) = debug_reading(file, "n\np a\nc\n", "hello\n");
+
+		assert!(finished);
+		assert!(output.contains("hello : str"));
+		assert!(output.ends_with("hello\n"));
+	}
+}
@@ -1,35 +1,130 @@
 use std::ops::Range;
-use std::process::exit;
 
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
 use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
 
-pub fn error(
-	name: &str,
-	file: &str,
-	code: &str,
-	message: &str,
-	note: &str,
-	range: &Range<usize>,
-) -> ! {
+pub type Trace = Vec<(String, Range<usize>)>;
+
+#[derive(Debug, Clone)]
+pub struct Error {
+	pub code: String,
+	pub message: String,
+	pub note: String,
+	pub range: Range<usize>,
+	/// Name and call site of every function on the stack when the error was
+	/// raised, outermost first.
+	pub trace: Trace,
+}
+
+impl Error {
+	pub fn new(code: &str, message: &str, note: &str, range: Range<usize>) -> Self {
+		Self {
+			code: code.to_string(),
+			message: message.to_string(),
+			note: note.to_string(),
+			range,
+			trace: vec![],
+		}
+	}
+
+	pub fn with_trace(mut self, trace: Trace) -> Self {
+		self.trace = trace;
+		self
+	}
+
+	pub fn diagnostic(&self, file_id: usize, file: &str) -> Diagnostic<usize> {
+		let mut labels = vec![Label::primary(file_id, self.range.clone()).with_message(&self.note)];
+		let mut notes = vec!["note: ".to_owned() + &self.note];
+
+		let mut frames: Vec<(&(String, Range<usize>), usize)> = vec![];
+		for frame in self.trace.iter().rev() {
+			match frames.last_mut() {
+				Some((last, count)) if *last == frame => *count += 1,
+				_ => frames.push((frame, 1)),
+			}
+		}
+
+		for ((function, call), count) in frames {
+			labels.push(
+				Label::secondary(file_id, call.clone())
+					.with_message(format!("in `{function}` called from here")),
+			);
+
+			let line = file.get(..call.start).unwrap_or(file).matches('\n').count() + 1;
+			notes.push(if count > 1 {
+				format!("in `{function}` called from line {line} ({count} times)")
+			} else {
+				format!("in `{function}` called from line {line}")
+			});
+		}
+
+		Diagnostic::error()
+			.with_message(&self.message)
+			.with_code("E".to_owned() + &self.code)
+			.with_labels(labels)
+			.with_notes(notes)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+	pub code: String,
+	pub message: String,
+	pub note: String,
+	pub range: Range<usize>,
+}
+
+impl Warning {
+	pub fn new(code: &str, message: &str, note: &str, range: Range<usize>) -> Self {
+		Self {
+			code: code.to_string(),
+			message: message.to_string(),
+			note: note.to_string(),
+			range,
+		}
+	}
+
+	pub fn diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+		Diagnostic::warning()
+			.with_message(&self.message)
+			.with_code("W".to_owned() + &self.code)
+			.with_labels(vec![
+				Label::primary(file_id, self.range.clone()).with_message(&self.note)
+			])
+	}
+}
+
+/// Reports every error in `errors` without exiting.
+pub fn report(name: &str, file: &str, errors: &[Error]) {
+	emit(name, file, |file_id| {
+		errors
+			.iter()
+			.map(|error| error.diagnostic(file_id, file))
+			.collect()
+	});
+}
+
+/// Reports every warning in `warnings`.
+pub fn warn(name: &str, file: &str, warnings: &[Warning]) {
+	emit(name, file, |file_id| {
+		warnings
+			.iter()
+			.map(|warning| warning.diagnostic(file_id))
+			.collect()
+	});
+}
+
+fn emit(name: &str, file: &str, diagnostics: impl Fn(usize) -> Vec<Diagnostic<usize>>) {
 	let mut files = SimpleFiles::new();
 
 	let file_id = files.add(name, file);
 
-	let diagnostic = Diagnostic::error()
-		.with_message(message)
-		.with_code("E".to_owned() + code)
-		.with_labels(vec![
-			Label::primary(file_id, range.clone()).with_message(note)
-		])
-		.with_notes(vec!["note: ".to_owned() + note]);
-
-	let writer = StandardStream::stderr(ColorChoice::Always);
+	let writer = StandardStream::stderr(ColorChoice::Auto);
 	let config = codespan_reporting::term::Config::default();
 
-	term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
-
-	exit(1)
+	for diagnostic in diagnostics(file_id) {
+		term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
+	}
 }
@@ -0,0 +1,449 @@
+use crate::{
+	ast::{Ast, AstNode, Expression},
+	data,
+	error::Error,
+	lexer::Lexer,
+	parser::Parser,
+	token::{Span, Token, Tokens},
+};
+
+const INDENT: &str = "    ";
+
+/// Binding power of `-x` and `!x`, between `*` and `^`.
+const UNARY: u16 = 4;
+
+/// Formats `file` into canonical Oxido source, keeping its comments.
+pub fn format(file: &str) -> Result<String, Vec<Error>> {
+	let mut lexer = Lexer::new(file);
+	let tokens = lexer.run().map_err(|e| vec![e])?.to_vec();
+	let comments = lexer.comments().to_vec();
+
+	let (ast, errors) = Parser::new().run(tokens.clone());
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	let mut formatter = Formatter {
+		file,
+		tokens,
+		comments,
+		comment: 0,
+		output: String::new(),
+		indent: 0,
+		last: None,
+	};
+	formatter.statements(&ast, file.len());
+
+	Ok(formatter.output)
+}
+
+struct Formatter<'a> {
+	file: &'a str,
+	tokens: Tokens,
+	comments: Vec<(String, Span)>,
+	/// Index of the next comment to be written.
+	comment: usize,
+	output: String,
+	indent: usize,
+	/// End of the last statement or comment written in the current block.
+	last: Option<usize>,
+}
+
+impl Formatter<'_> {
+	fn statements(&mut self, ast: &Ast, end: usize) {
+		for (node, range) in ast {
+			self.comments(range.start);
+
+			self.line(range.start);
+			self.node(node, range.start, range.end);
+
+			let mut last = range.end;
+			if let Some((comment, span)) = self.comments.get(self.comment) {
+				let between = self.file.get(range.end..span.range.start);
+				if between.is_some_and(|between| !between.contains('\n')) {
+					self.output += " ";
+					self.output += comment;
+					last = span.range.end;
+					self.comment += 1;
+				}
+			}
+
+			self.output += "\n";
+			self.last = Some(last);
+		}
+
+		self.comments(end);
+	}
+
+	/// Writes every comment before `offset` on a line of its own.
+	fn comments(&mut self, offset: usize) {
+		while let Some((comment, span)) = self.comments.get(self.comment) {
+			if span.range.start >= offset {
+				break;
+			}
+			let (comment, range) = (comment.clone(), span.range.clone());
+
+			self.line(range.start);
+			self.output += &comment;
+			self.output += "\n";
+
+			self.last = Some(range.end);
+			self.comment += 1;
+		}
+	}
+
+	/// Starts a line for something at `start` in the source, keeping a blank
+	/// line before it if there was one.
+	fn line(&mut self, start: usize) {
+		if let Some(last) = self.last {
+			let between = self.file.get(last..start).unwrap_or_default();
+			if between.matches('\n').count() > 1 {
+				self.output += "\n";
+			}
+		}
+
+		self.output += &INDENT.repeat(self.indent);
+	}
+
+	fn block(&mut self, ast: &Ast, end: usize) {
+		let empty = match self.comments.get(self.comment) {
+			Some((_, span)) => span.range.start >= end,
+			None => true,
+		};
+		if ast.is_empty() && empty {
+			self.output += "{}";
+			return;
+		}
+
+		self.output += "{\n";
+		self.indent += 1;
+		self.last = None;
+
+		self.statements(ast, end);
+
+		self.indent -= 1;
+		self.output += &INDENT.repeat(self.indent);
+		self.output += "}";
+	}
+
+	fn node(&mut self, node: &AstNode, start: usize, end: usize) {
+		match node {
+			AstNode::Assignment(ident, datatype, expression) => {
+				self.output += &format!("let {ident}: {datatype} = ");
+				self.expression(expression, 0);
+				self.output += ";";
+			}
+			AstNode::ReAssignment(ident, expression) => {
+				self.output += &format!("{ident} = ");
+				self.expression(expression, 0);
+				self.output += ";";
+			}
+			AstNode::VecReAssignment(ident, index, expression) => {
+				self.output += &format!("{ident}[");
+				self.expression(index, 0);
+				self.output += "] = ";
+				self.expression(expression, 0);
+				self.output += ";";
+			}
+			AstNode::If(condition, then) => {
+				self.output += "if ";
+				self.expression(condition, 0);
+				self.output += " ";
+				self.block(then, end);
+			}
+			AstNode::IfElse(condition, then, otherwise) => {
+				self.output += "if ";
+				self.expression(condition, 0);
+				self.output += " ";
+
+				// the `else` ending the first block is the first one after its
+				// statements
+				let after = then.last().map_or(start, |(_, range)| range.end);
+				let r#else = self
+					.tokens
+					.iter()
+					.find(|(token, span)| *token == Token::Else && span.range.start >= after)
+					.map_or(end, |(_, span)| span.range.start);
+
+				self.block(then, r#else);
+				self.output += " else ";
+
+				match otherwise.as_slice() {
+					[(node @ (AstNode::If(..) | AstNode::IfElse(..)), range)]
+						if self.file.get(r#else..range.start).map(str::trim) == Some("else") =>
+					{
+						self.node(node, range.start, range.end)
+					}
+					_ => self.block(otherwise, end),
+				}
+			}
+			AstNode::Loop(body) => {
+				self.output += "loop ";
+				self.block(body, end);
+			}
+			AstNode::FunctionCall(name, params) => {
+				self.call(name, params);
+				self.output += ";";
+			}
+			AstNode::FunctionDeclaration(name, params, datatype, body) => {
+				let params = params
+					.iter()
+					.map(|param| format!("{}: {}", param.name, param.datatype))
+					.collect::<Vec<_>>();
+
+				self.output += &format!("fn {name}({})", params.join(", "));
+				if let Some(datatype) = datatype {
+					self.output += &format!(" -> {datatype}");
+				}
+				self.output += " ";
+				self.block(body, end);
+			}
+			AstNode::Test(name, body) => {
+				self.output += &format!("test fn {name}() ");
+				self.block(body, end);
+			}
+			AstNode::Break => self.output += "break;",
+			AstNode::Return(expression) => {
+				self.output += "return ";
+				self.expression(expression, 0);
+				self.output += ";";
+			}
+			AstNode::Exit(expression) => {
+				self.output += "exit ";
+				self.expression(expression, 0);
+				self.output += ";";
+			}
+			AstNode::Error => {}
+		}
+	}
+
+	/// Writes `expression`, parenthesised if it binds looser than `power`.
+	fn expression(&mut self, expression: &Expression, power: u16) {
+		match expression {
+			Expression::BinaryOperation(lhs, op, rhs) => {
+				let own = binding_power(op);
+				let parens = own < power;
+
+				if parens {
+					self.output += "(";
+				}
+
+				// operators are left associative except `^`
+				let (left, right) = if *op == Token::Power {
+					(own + 1, own)
+				} else {
+					(own, own + 1)
+				};
+
+				self.expression(lhs, left);
+				self.output += &format!(" {} ", op.as_string());
+				self.expression(rhs, right);
+
+				if parens {
+					self.output += ")";
+				}
+			}
+			Expression::UnaryOperation(op, operand) => {
+				let parens = UNARY < power;
+				if parens {
+					self.output += "(";
+				}
+
+				self.output += &op.as_string();
+				// `- -x` written as `--x` would read as one operator
+				if matches!(operand.as_ref(), Expression::UnaryOperation(..)) || negative(operand) {
+					self.output += "(";
+					self.expression(operand, 0);
+					self.output += ")";
+				} else {
+					self.expression(operand, UNARY);
+				}
+
+				if parens {
+					self.output += ")";
+				}
+			}
+			// a negative literal reads as a unary minus, which `^` binds tighter than
+			Expression::Int(_) | Expression::Float(_) if negative(expression) && UNARY < power => {
+				self.output += "(";
+				self.expression(expression, 0);
+				self.output += ")";
+			}
+			Expression::Str(string) => self.output += &escape(string),
+			Expression::Int(i) => self.output += &i.to_string(),
+			Expression::Float(f) => self.output += &data::float(*f),
+			Expression::Bool(b) => self.output += &b.to_string(),
+			Expression::FunctionCall(name, params) => self.call(name, params),
+			Expression::Identifier(ident) => self.output += ident,
+			Expression::Vector(items, _) => {
+				self.output += "[";
+				self.list(items);
+				self.output += "]";
+			}
+			Expression::VecIndex(ident, index) => {
+				self.output += &format!("{ident}[");
+				self.expression(index, 0);
+				self.output += "]";
+			}
+			Expression::VecSlice(ident, start, end) => {
+				self.output += &format!("{ident}[");
+				self.expression(start, 0);
+				self.output += "..";
+				self.expression(end, 0);
+				self.output += "]";
+			}
+			Expression::Block(ast, expression) => {
+				let last = self.last;
+
+				self.output += "{\n";
+				self.indent += 1;
+				self.last = None;
+
+				self.statements(ast, 0);
+				self.output += &INDENT.repeat(self.indent);
+				self.expression(expression, 0);
+				self.output += "\n";
+
+				self.indent -= 1;
+				self.output += &INDENT.repeat(self.indent);
+				self.output += "}";
+
+				self.last = last;
+			}
+		}
+	}
+
+	fn call(&mut self, name: &str, params: &[Expression]) {
+		self.output += name;
+		self.output += "(";
+		self.list(params);
+		self.output += ")";
+	}
+
+	fn list(&mut self, expressions: &[Expression]) {
+		for (i, expression) in expressions.iter().enumerate() {
+			if i != 0 {
+				self.output += ", ";
+			}
+			self.expression(expression, 0);
+		}
+	}
+}
+
+fn negative(expression: &Expression) -> bool {
+	match expression {
+		Expression::Int(i) => *i < 0,
+		Expression::Float(f) => f.is_sign_negative(),
+		_ => false,
+	}
+}
+
+fn binding_power(op: &Token) -> u16 {
+	match op {
+		Token::Addition | Token::Subtraction => 2,
+		Token::Multiplication | Token::Division => 3,
+		Token::Power => 4,
+		_ => 1,
+	}
+}
+
+fn escape(string: &str) -> String {
+	let mut escaped = String::from("\"");
+
+	for ch in string.chars() {
+		match ch {
+			'"' => escaped += "\\\"",
+			'\\' => escaped += "\\\\",
+			'\n' => escaped += "\\n",
+			'\t' => escaped += "\\t",
+			'\r' => escaped += "\\r",
+			'\0' => escaped += "\\0",
+			ch if ch.is_control() => escaped += &format!("\\u{{{:x}}}", ch as u32),
+			ch => escaped.push(ch),
+		}
+	}
+
+	escaped + "\""
+}
+
+#[cfg(test)]
+mod tests {
+	use super::format;
+
+	/// Checks that `file` is already formatted, and so is left unchanged.
+	fn unchanged(file: &str) {
+		assert_eq!(format(file).unwrap(), file);
+	}
+
+	#[test]
+	fn round_trip() {
+		unchanged(
+			"\
+let x: int = 3;
+print(2 * -x, -x ^ 2, (-3) ^ 2, -(-x), !(x > 2));
+let v: vec<int> = [1, 2, 3];
+v[0] = v[1];
+let w: vec<int> = v[1..3];
+
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+
+loop {
+    break;
+}
+",
+		);
+	}
+
+	#[test]
+	fn idempotent() {
+		let file = "let   a:int=1+2*3;fn f(x:int)->int{return (x+1)*2;}\n\n\n// done\nif a>1{print(a);}else{print( \"b\" );}";
+		let once = format(file).unwrap();
+
+		assert_eq!(format(&once).unwrap(), once);
+	}
+
+	#[test]
+	fn comments() {
+		unchanged(
+			"\
+// on its own line
+let a: int = 1; // at the end of a line
+
+if a == 1 {
+    // inside a block
+    print(a); // after a statement
+}
+// at the end of the file
+",
+		);
+	}
+
+	#[test]
+	fn else_if() {
+		unchanged(
+			"\
+if a == 1 {
+    print(1);
+} else if a == 2 {
+    print(2);
+} else if a == 3 {
+    print(3);
+} else {
+    print(0);
+}
+",
+		);
+	}
+
+	#[test]
+	fn blank_lines() {
+		let file = "let a: int = 1;\n\n\n\nlet b: int = 2;\nlet c: int = 3;\n";
+
+		assert_eq!(
+			format(file).unwrap(),
+			"let a: int = 1;\n\nlet b: int = 2;\nlet c: int = 3;\n"
+		);
+	}
+}
@@ -0,0 +1,242 @@
+use std::borrow::Cow;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper, Result};
+use unicode_xid::UnicodeXID;
+
+use crate::{
+	lexer::Lexer,
+	repl::{balanced, COMMANDS},
+	standardlibrary::StandardLibrary,
+	token::Token,
+};
+
+const KEYWORDS: [&str; 10] = [
+	"let", "if", "else", "loop", "fn", "exit", "break", "return", "true", "false",
+];
+
+/// Completion, highlighting and validation for the REPL line editor.
+#[derive(Debug, Default)]
+pub struct OxidoHelper {
+	/// Variables and functions declared in the session so far.
+	pub names: Vec<String>,
+}
+
+impl Helper for OxidoHelper {}
+
+impl Completer for OxidoHelper {
+	type Candidate = Pair;
+
+	fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> Result<(usize, Vec<Pair>)> {
+		if line.starts_with(':') && !line[..pos].contains(char::is_whitespace) {
+			return Ok((0, candidates(COMMANDS.iter().copied(), &line[..pos])));
+		}
+
+		let start = line[..pos]
+			.char_indices()
+			.rev()
+			.take_while(|(_, ch)| ch.is_xid_continue() || *ch == ':')
+			.last()
+			.map_or(pos, |(i, _)| i);
+
+		let names = KEYWORDS
+			.iter()
+			.chain(StandardLibrary::default().constants())
+			.copied()
+			.chain(self.names.iter().map(String::as_str));
+
+		Ok((start, candidates(names, &line[start..pos])))
+	}
+}
+
+fn candidates<'a>(names: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<Pair> {
+	let mut names = names
+		.filter(|name| name.starts_with(prefix))
+		.collect::<Vec<_>>();
+	names.sort_unstable();
+	names.dedup();
+
+	names
+		.into_iter()
+		.map(|name| Pair {
+			display: name.to_string(),
+			replacement: name.to_string(),
+		})
+		.collect()
+}
+
+impl Hinter for OxidoHelper {
+	type Hint = String;
+}
+
+impl Highlighter for OxidoHelper {
+	fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+		if line.starts_with(':') {
+			return Cow::Borrowed(line);
+		}
+
+		let mut lexer = Lexer::new(line);
+		let tokens = match lexer.run() {
+			Ok(tokens) => tokens,
+			Err(_) => return Cow::Borrowed(line),
+		};
+
+		let mut highlighted = String::with_capacity(line.len());
+		let mut last = 0;
+
+		for (token, span) in tokens {
+			let colour = match token {
+				Token::Let
+				| Token::If
+				| Token::Else
+				| Token::Loop
+				| Token::Fn
+				| Token::Exit
+				| Token::Break
+				| Token::Return => "\x1b[35m",
+				Token::Str(_) => "\x1b[32m",
+				Token::Int(_) | Token::Float(_) | Token::Bool(_) => "\x1b[33m",
+				Token::DataType(_) => "\x1b[36m",
+				Token::FunctionName(_) => "\x1b[34m",
+				_ => "",
+			};
+
+			highlighted += &line[last..span.range.start];
+			if colour.is_empty() {
+				highlighted += &line[span.range.clone()];
+			} else {
+				highlighted += colour;
+				highlighted += &line[span.range.clone()];
+				highlighted += "\x1b[0m";
+			}
+			last = span.range.end;
+		}
+		highlighted += &line[last..];
+
+		Cow::Owned(highlighted)
+	}
+
+	fn highlight_char(&self, _: &str, _: usize) -> bool {
+		true
+	}
+}
+
+impl Validator for OxidoHelper {
+	fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult> {
+		if balanced(ctx.input()) {
+			Ok(ValidationResult::Valid(None))
+		} else {
+			Ok(ValidationResult::Incomplete)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::OxidoHelper;
+	use crate::standardlibrary::StandardLibrary;
+	use rustyline::{
+		completion::Completer,
+		highlight::Highlighter,
+		history::{DefaultHistory, History},
+		Context,
+	};
+
+	fn helper() -> OxidoHelper {
+		let mut names = StandardLibrary::default()
+			.names()
+			.into_iter()
+			.map(String::from)
+			.collect::<Vec<_>>();
+		names.extend(["counter", "count_words"].map(String::from));
+
+		OxidoHelper { names }
+	}
+
+	/// Start of the word being completed at the end of `line`, and what it
+	/// can be completed to.
+	fn complete(line: &str) -> (usize, Vec<String>) {
+		let history = DefaultHistory::new();
+		let (start, pairs) = helper()
+			.complete(line, line.len(), &Context::new(&history))
+			.unwrap();
+
+		(
+			start,
+			pairs.into_iter().map(|pair| pair.replacement).collect(),
+		)
+	}
+
+	#[test]
+	fn builtins() {
+		assert_eq!(
+			complete("pri"),
+			(0, vec![String::from("print"), String::from("println")])
+		);
+		assert_eq!(complete("lo").1, ["loop"]);
+	}
+
+	#[test]
+	fn namespaced() {
+		assert_eq!(
+			complete("let n: int = string::le"),
+			(13, vec![String::from("string::len")])
+		);
+
+		let (start, names) = complete("println(vector::");
+		assert_eq!(start, 8);
+		assert!(names.contains(&String::from("vector::push")));
+		assert!(names.iter().all(|name| name.starts_with("vector::")));
+
+		assert_eq!(complete("math::P").1, ["math::PI"]);
+		assert!(complete("fs::").1.contains(&String::from("fs::read_file")));
+	}
+
+	#[test]
+	fn session_names_and_commands() {
+		assert_eq!(complete("println(cou").1, ["count_words", "counter"]);
+		assert_eq!(complete(":ty"), (0, vec![String::from(":type")]));
+		assert!(complete("nothing_like_this").1.is_empty());
+	}
+
+	#[test]
+	fn highlighting() {
+		let helper = helper();
+
+		assert_eq!(
+			helper.highlight("let s: str = \"fn\";", 0),
+			"\x1b[35mlet\x1b[0m s: \x1b[36mstr\x1b[0m = \x1b[32m\"fn\"\x1b[0m;"
+		);
+		assert_eq!(
+			helper.highlight("if true { return 1; }", 0),
+			"\x1b[35mif\x1b[0m \x1b[33mtrue\x1b[0m { \x1b[35mreturn\x1b[0m \x1b[33m1\x1b[0m; }"
+		);
+
+		// lines which do not lex and commands are left as they are
+		assert_eq!(
+			helper.highlight("let s: str = \"open", 0),
+			"let s: str = \"open"
+		);
+		assert_eq!(helper.highlight(":type 1", 0), ":type 1");
+	}
+
+	#[test]
+	fn multi_line_history() {
+		let path = std::env::temp_dir().join(format!("oxido-history-{}", std::process::id()));
+		let entry = "fn f() {\n\treturn 1;\n}";
+
+		let mut history = DefaultHistory::new();
+		history.add(entry).unwrap();
+		history.save(&path).unwrap();
+
+		let mut loaded = DefaultHistory::new();
+		loaded.load(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+
+		assert_eq!(loaded.len(), 1);
+		assert_eq!(loaded.iter().next().unwrap(), entry);
+	}
+}
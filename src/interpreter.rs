@@ -1,130 +1,379 @@
-use std::{collections::HashMap, ops::Range, process};
+use std::{
+	cell::RefCell,
+	collections::HashMap,
+	fmt::Debug,
+	io::{Read, Write},
+	ops::Range,
+	rc::Rc,
+	time::Instant,
+};
 
 use crate::{
 	ast::{Ast, AstNode, Expression},
-	data::{Data, DataType, Function, Variable},
-	error::error,
-	standardlibrary::StandardLibrary,
+	data::{Data, DataType, Function, Value, Variable},
+	error::{Error, Trace},
+	sandbox::{Cancel, Capabilities, Limits},
+	standardlibrary::{Signature, StandardLibrary},
 	token::Token,
 };
 
-#[derive(Debug, Clone)]
-pub struct Interpreter<'a> {
-	name: &'a str,
-	file: String,
+/// Observes an [`Interpreter`] as it runs, used by the debugger and profiler.
+pub trait Hook: Debug {
+	/// Called before the statement at `range` is run.
+	fn statement(&mut self, interpreter: &Interpreter, range: &Range<usize>);
+
+	/// Called before the body of the function `name` is run.
+	fn enter(&mut self, _name: &str) {}
+
+	/// Called after the function `name` returns.
+	fn exit(&mut self, _name: &str) {}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
 	stop: bool,
 	returned: Option<Data>,
+	/// Whether `exit` was run, it unwinds like an error until `run` stops it.
+	exited: bool,
+	limits: Limits,
+	/// Statements and loop iterations run so far in this run.
+	instructions: u64,
+	/// Bytes taken up by the values of variables, only counted when limited.
+	memory: usize,
+	/// When the time limit runs out, set by the first statement of a run.
+	deadline: Option<Instant>,
+	cancel: Cancel,
 	variables: HashMap<String, Variable>,
 	functions: HashMap<String, Function>,
-	std: StandardLibrary<'a>,
+	stack: Trace,
+	std: StandardLibrary,
+	hook: Option<Rc<RefCell<dyn Hook>>>,
 }
 
-impl<'a> Interpreter<'a> {
-	pub fn new(name: &'a str, file: String) -> Self {
+impl Interpreter {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates an interpreter which only lets programs do what `capabilities`
+	/// allows, and stops them once they exceed `limits`.
+	pub fn sandboxed(capabilities: Capabilities, limits: Limits) -> Self {
 		Self {
-			name,
-			file: file.clone(),
-			stop: false,
-			returned: None,
-			variables: HashMap::new(),
-			functions: HashMap::new(),
-			std: StandardLibrary::new(name, file),
+			std: StandardLibrary::new(capabilities),
+			limits,
+			..Self::default()
 		}
 	}
 
-	pub fn set_file(&mut self, file: String) {
-		self.file = file;
+	pub fn variables(&self) -> &HashMap<String, Variable> {
+		&self.variables
+	}
+
+	pub fn functions(&self) -> &HashMap<String, Function> {
+		&self.functions
+	}
+
+	/// Names and call sites of the functions being run, outermost first.
+	pub fn stack(&self) -> &Trace {
+		&self.stack
+	}
+
+	pub fn set_hook(&mut self, hook: Rc<RefCell<dyn Hook>>) {
+		self.hook = Some(hook);
+	}
+
+	/// Functions the program can call besides its own, see
+	/// [`StandardLibrary::names`].
+	pub fn library(&self) -> &StandardLibrary {
+		&self.std
+	}
+
+	/// Makes `function` callable from programs as `name`, see
+	/// [`StandardLibrary::register_fn`].
+	pub fn register_fn<F>(&mut self, name: &str, signature: Signature, function: F)
+	where
+		F: Fn(Vec<Data>) -> Result<Option<Data>, String> + 'static,
+	{
+		self.std.register_fn(name, signature, function);
+	}
+
+	/// Lets `cancel` stop the program from another thread.
+	pub fn set_cancel(&mut self, cancel: Cancel) {
+		self.cancel = cancel;
+	}
+
+	/// Sets the arguments returned by `args`, which returns none unless set.
+	pub fn set_args(&mut self, args: Vec<String>) {
+		let signature = Signature::new(vec![], Some(Vec::<String>::datatype()));
+
+		self.register_fn("args", signature, move |_| {
+			Ok(Some(args.clone().into_data()?))
+		});
+	}
+
+	/// Sets where the program reads input from, stdin unless set.
+	pub fn set_stdin(&mut self, stdin: Rc<RefCell<dyn Read>>) {
+		self.std.set_stdin(stdin);
+	}
+
+	/// Sets where the program writes output to, stdout unless set.
+	pub fn set_stdout(&mut self, stdout: Rc<RefCell<dyn Write>>) {
+		self.std.set_stdout(stdout);
+	}
+
+	/// Sets where the program writes errors to, stderr unless set.
+	pub fn set_stderr(&mut self, stderr: Rc<RefCell<dyn Write>>) {
+		self.std.set_stderr(stderr);
+	}
+
+	/// Whether the program ran `exit`, nothing more is run once it has.
+	pub fn exited(&self) -> bool {
+		self.exited
+	}
+
+	/// Status the program ends with, set by `exit` and `exit_code`.
+	pub fn exit_code(&self) -> i32 {
+		self.std.code()
 	}
 
-	pub fn run(&mut self, ast: Ast) {
-		let mut stream = ast.into_iter().peekable();
-		loop {
-			if stream.peek().is_none() {
+	/// Runs `ast`, on error the interpreter is left usable with every variable
+	/// and function declared before the error.
+	pub fn run(&mut self, ast: Ast) -> Result<(), Error> {
+		self.restart();
+
+		for node in ast {
+			if self.exited {
 				break;
 			}
+			if let Err(error) = self.match_node(node) {
+				return self.recover(error);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Starts counting the instruction and time limits again.
+	fn restart(&mut self) {
+		self.instructions = 0;
+		self.deadline = None;
+	}
+
+	/// Resets the state left by a statement which failed or exited.
+	fn recover<T: Default>(&mut self, error: Error) -> Result<T, Error> {
+		self.stop = false;
+		self.returned = None;
+		self.stack.clear();
+
+		if self.exited {
+			Ok(T::default())
+		} else {
+			Err(error)
+		}
+	}
+
+	/// Evaluates `expression`, calls to functions which do not return a value
+	/// evaluate to `None`.
+	pub fn evaluate(
+		&mut self,
+		expression: Expression,
+		range: &Range<usize>,
+	) -> Result<Option<Data>, Error> {
+		if self.exited {
+			return Ok(None);
+		}
+		self.restart();
+
+		let result = match expression {
+			Expression::FunctionCall(name, params) => self.call(name, params, range),
+			expression => self.parse_expression(expression, None, range).map(Some),
+		};
+
+		result.or_else(|error| self.recover(error))
+	}
+
+	fn error(&self, code: &str, message: &str, note: &str, range: &Range<usize>) -> Error {
+		Error::new(code, message, note, range.clone()).with_trace(self.stack.clone())
+	}
+
+	/// Counts a statement or loop iteration against the limits, and stops the
+	/// program if it was cancelled.
+	fn tick(&mut self, range: &Range<usize>) -> Result<(), Error> {
+		self.cancelled(range)?;
+
+		self.instructions += 1;
+		if let Some(max) = self.limits.instructions {
+			if self.instructions > max {
+				return Err(self.error(
+					"0016",
+					"execution limit exceeded",
+					&format!("the program ran more than {max} instructions"),
+					range,
+				));
+			}
+		}
+
+		if let Some(time) = self.limits.time {
+			let deadline = *self.deadline.get_or_insert_with(|| Instant::now() + time);
+			if Instant::now() > deadline {
+				return Err(self.error(
+					"0016",
+					"execution limit exceeded",
+					&format!("the program ran for longer than {time:?}"),
+					range,
+				));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn cancelled(&self, range: &Range<usize>) -> Result<(), Error> {
+		if self.cancel.take() {
+			return Err(self.error(
+				"0017",
+				"execution cancelled",
+				"the program was stopped before it finished",
+				range,
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Stores `variable` as `ident`, checking the memory limit.
+	fn store(
+		&mut self,
+		ident: String,
+		variable: Variable,
+		range: &Range<usize>,
+	) -> Result<(), Error> {
+		if let Some(max) = self.limits.memory {
+			let old = self.variables.get(&ident).map_or(0, |old| old.data.size());
+			let memory = self.memory - old + variable.data.size();
+
+			if memory > max {
+				return Err(self.error(
+					"0016",
+					"execution limit exceeded",
+					&format!("the values of variables take up more than {max} bytes"),
+					range,
+				));
+			}
+			self.memory = memory;
+		}
+
+		self.variables.insert(ident, variable);
+		Ok(())
+	}
+
+	/// Checks that `size` more bytes fit in what the memory limit has left,
+	/// before a value that large is built.
+	fn reserve(&self, size: usize, range: &Range<usize>) -> Result<(), Error> {
+		if let Some(max) = self.limits.memory {
+			let left = max - self.memory;
+
+			if size > left {
+				return Err(self.error(
+					"0016",
+					"execution limit exceeded",
+					&format!("the value being built takes up more than the {left} bytes left of {max}"),
+					range,
+				));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn block(&mut self, statements: Ast) -> Result<(), Error> {
+		for node in statements {
+			self.match_node(node)?;
 
-			self.match_node(stream.next().unwrap());
+			if self.stop || self.returned.is_some() {
+				break;
+			}
 		}
+
+		Ok(())
 	}
 
-	fn match_node(&mut self, node: (AstNode, Range<usize>)) {
+	fn match_node(&mut self, node: (AstNode, Range<usize>)) -> Result<(), Error> {
 		if self.stop || self.returned.is_some() {
-			return;
+			return Ok(());
+		}
+		self.tick(&node.1)?;
+		if let Some(hook) = self.hook.clone() {
+			hook.borrow_mut().statement(self, &node.1);
+			// the debugger cancels the program when told to quit
+			self.cancelled(&node.1)?;
 		}
 		match node.0 {
 			AstNode::Assignment(ident, datatype, expression) => {
-				let data = self.parse_expression(expression, Some(datatype.clone()), &node.1);
+				let data = self.parse_expression(expression, Some(datatype.clone()), &node.1)?;
 				let expr_type = data.r#type();
 				if datatype != expr_type {
-					error(
-						self.name,
-						&self.file,
-						"E00011",
+					return Err(self.error(
+						"0011",
 						"incorrect data type",
-						&format!(
-							"mismatched data types expected {} fond {:?}",
-							datatype, data
-						),
+						&format!("mismatched data types expected {} found {}", datatype, data),
 						&node.1,
-					)
+					));
 				}
-				self.variables.insert(ident, Variable::new(datatype, data));
+				self.store(ident, Variable::new(datatype, data), &node.1)?;
 			}
 			AstNode::ReAssignment(ident, expression) => {
-				if !self.variables.contains_key(&ident) {
-					error(
-						self.name,
-						&self.file,
-						"0005",
-						"undeclared variable",
-						"attempted to access value of undeclared variable",
-						&node.1,
-					);
-				}
-				let datatype = self.variables.get(&ident).unwrap().datatype.clone();
-				let data = self.parse_expression(expression, Some(datatype.clone()), &node.1);
+				let datatype = match self.variables.get(&ident) {
+					Some(variable) => variable.datatype.clone(),
+					None => {
+						return Err(self.error(
+							"0005",
+							"undeclared variable",
+							"attempted to access value of undeclared variable",
+							&node.1,
+						))
+					}
+				};
+				let data = self.parse_expression(expression, Some(datatype.clone()), &node.1)?;
 				if datatype != data.r#type() {
-					error(
-						self.name,
-						&self.file,
-						"E00011",
+					return Err(self.error(
+						"0011",
 						"incorrect data type",
 						&format!("mismatched data types expected {} found {}", datatype, data),
 						&node.1,
-					)
+					));
 				}
-				self.variables.insert(ident, Variable::new(datatype, data));
+				self.store(ident, Variable::new(datatype, data), &node.1)?;
 			}
 			AstNode::VecReAssignment(ident, index, expression) => {
-				let data = self.parse_expression(expression, None, &node.1);
-				let index = self.parse_expression(index, None, &node.1);
-				let mut variable = self.variables.get(&ident).unwrap().clone();
+				let data = self.parse_expression(expression, None, &node.1)?;
+				let index = self.parse_expression(index, None, &node.1)?;
+				let mut variable = self.variable(&ident, &node.1)?;
 
 				if let Data::Vector(mut vec, datatype) = variable.data {
 					if let Data::Int(index) = index {
-						if index as usize > vec.len() {
-							error(
-								self.name,
-								&self.file,
-								"E0006",
-								"index out of bounds",
+						if index < 0 || index as usize > vec.len() {
+							return Err(self.error(
+								"0006",
 								"index out of bounds",
+								&format!(
+									"index {index} is out of bounds for vector of length {}, use `vector::push` to add to it",
+									vec.len()
+								),
 								&node.1,
-							);
+							));
 						}
 
-						if datatype != DataType::Vector(Box::new(data.r#type())) {
-							error(
-								self.name,
-								&self.file,
-								"E00011",
+						if datatype != data.r#type() {
+							return Err(self.error(
+								"0011",
 								"incorrect data type",
 								&format!("mismatched data types expected {} found {}", datatype, data),
 								&node.1,
-							)
+							));
 						}
 
-						if vec.len() == index.try_into().unwrap() {
+						if vec.len() == index as usize {
 							vec.push(data);
 						} else {
 							vec[index as usize] = data;
@@ -132,21 +381,17 @@ impl<'a> Interpreter<'a> {
 
 						variable.data = Data::Vector(vec, datatype);
 
-						self.variables.insert(ident, variable.clone());
+						self.store(ident, variable, &node.1)?;
 					} else {
-						error(
-							self.name,
-							&self.file,
+						return Err(self.error(
 							"0002",
 							&format!("mismatched data types, expected `int` found {}", index),
 							"a value of type `int` was expected",
 							&node.1,
-						);
+						));
 					}
 				} else {
-					error(
-						self.name,
-						&self.file,
+					return Err(self.error(
 						"0002",
 						&format!(
 							"mismatched data types, expected `vector` found {}",
@@ -154,155 +399,54 @@ impl<'a> Interpreter<'a> {
 						),
 						"a value of type `vector` was expected",
 						&node.1,
-					);
+					));
 				}
 			}
 			AstNode::If(condition, statements) => {
-				let data = self.parse_expression(condition, None, &node.1);
+				let data = self.parse_expression(condition, None, &node.1)?;
 
 				if let Data::Bool(bool) = data {
 					if bool {
-						let mut stream = statements.into_iter().peekable();
-						loop {
-							if stream.peek().is_none() {
-								break;
-							}
-
-							self.match_node(stream.next().unwrap());
-						}
+						self.block(statements)?;
 					}
 				} else {
-					error(
-						self.name,
-						&self.file,
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `bool` found {}", data),
 						"a value of type `bool` was expected",
 						&node.1,
-					);
+					));
 				}
 			}
 			AstNode::IfElse(condition, then, otherwise) => {
-				let data = self.parse_expression(condition, None, &node.1);
+				let data = self.parse_expression(condition, None, &node.1)?;
 
 				if let Data::Bool(bool) = data {
-					let mut stream = if bool {
-						then.into_iter().peekable()
-					} else {
-						otherwise.into_iter().peekable()
-					};
-
-					loop {
-						if stream.peek().is_none() {
-							break;
-						}
-
-						self.match_node(stream.next().unwrap());
-					}
+					self.block(if bool { then } else { otherwise })?;
 				} else {
-					error(
-						self.name,
-						&self.file,
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `bool` found {}", data),
 						"a value of type `bool` was expected",
 						&node.1,
-					);
-				}
-			}
-			AstNode::Loop(statements) => {
-				let mut stream = statements.clone().into_iter().peekable();
-				loop {
-					if stream.peek().is_none() {
-						stream = statements.clone().into_iter().peekable();
-					}
-
-					if self.stop {
-						self.stop = false;
-						break;
-					}
-
-					if self.returned.is_some() {
-						break;
-					}
-
-					self.match_node(stream.next().unwrap());
+					));
 				}
 			}
-			AstNode::FunctionCall(name, params) => {
-				let mut args = vec![];
+			AstNode::Loop(statements) => loop {
+				self.tick(&node.1)?;
+				self.block(statements.clone())?;
 
-				for param in params {
-					args.push(self.parse_expression(param, None, &node.1))
+				if self.stop {
+					self.stop = false;
+					break;
 				}
 
-				if self.std.contains(&name) {
-					self.std.call(&name, &node.1, args);
-				} else if self.functions.contains_key(&*name) {
-					let function = self.functions.get(&*name).unwrap().to_owned();
-
-					if args.len() != function.params.len() {
-						error(
-							self.name,
-							&self.file,
-							"0004",
-							"not enough arguments were passed",
-							&format!(
-								"{} arguments were expected but {} were passed",
-								function.params.len(),
-								args.len()
-							),
-							&node.1,
-						);
-					}
-
-					for (i, arg) in args.iter().enumerate() {
-						let param = function.params.get(i).unwrap();
-
-						if param.datatype != arg.r#type() {
-							error(
-								self.name,
-								&self.file,
-								"E00011",
-								"incorrect data type",
-								&format!(
-									"mismatched data types expected {} found {}",
-									param.datatype, arg
-								),
-								&node.1,
-							)
-						}
-
-						self.variables.insert(
-							param.name.clone(),
-							Variable::new(param.datatype.clone(), arg.to_owned()),
-						);
-					}
-
-					let mut stream = function.statements.into_iter().peekable();
-
-					loop {
-						if stream.peek().is_none() {
-							break;
-						}
-
-						self.match_node(stream.next().unwrap());
-
-						if self.returned.is_some() {
-							self.returned = None;
-							break;
-						}
-					}
-				} else {
-					error(
-						self.name,
-						&self.file,
-						"0004",
-						"function does not exist",
-						"tried to call a function which does not exist",
-						&node.1,
-					);
+				if self.returned.is_some() {
+					break;
 				}
+			},
+			AstNode::FunctionCall(name, params) => {
+				self.call(name, params, &node.1)?;
 			}
 			AstNode::FunctionDeclaration(name, params, datatype, statements) => {
 				self.functions.insert(
@@ -313,135 +457,222 @@ impl<'a> Interpreter<'a> {
 			AstNode::Break => {
 				self.stop = true;
 			}
-			AstNode::Return(expr) => self.returned = Some(self.parse_expression(expr, None, &node.1)),
+			AstNode::Return(expr) => {
+				self.returned = Some(self.parse_expression(expr, None, &node.1)?);
+			}
 			AstNode::Exit(expr) => {
-				let data = self.parse_expression(expr, None, &node.1);
+				let data = self.parse_expression(expr, None, &node.1)?;
 
 				match data {
-					Data::Int(n) => process::exit(n),
+					Data::Int(n) => {
+						self.std.set_code(n);
+						self.exited = true;
+
+						return Err(self.error("0000", "exit", "the program exited", &node.1));
+					}
 					_ => {
-						error(
-							self.name,
-							&self.file,
+						return Err(self.error(
 							"0002",
 							&format!("mismatched data types, expected `int` found {}", data),
-							"a value of type `String` was expected",
+							"a value of type `int` was expected",
 							&node.1,
-						);
+						));
 					}
 				};
 			}
+			AstNode::Test(_, _) | AstNode::Error => {}
 		}
+
+		Ok(())
 	}
 
-	fn parse_function(&mut self, f: String, args: Vec<Expression>, pos: &Range<usize>) -> Data {
-		if self.std.contains(&f) {
-			let args = args
-				.iter()
-				.map(|f| self.parse_expression(f.clone(), None, pos))
-				.collect::<Vec<_>>();
-			return match self.std.call(&f, pos, args) {
-				Some(data) => data,
-				None => error(
-					self.name,
-					&self.file,
+	fn variable(&self, ident: &str, pos: &Range<usize>) -> Result<Variable, Error> {
+		if let Some(variable) = self.variables.get(ident) {
+			return Ok(variable.clone());
+		}
+
+		match self.std.constant(ident) {
+			Some(data) => Ok(Variable::new(data.r#type(), data)),
+			None => Err(self.error(
+				"0005",
+				&format!("undeclared variable `{ident}`"),
+				"attempted to access value of undeclared variable",
+				pos,
+			)),
+		}
+	}
+
+	/// Calls the function `f`, returning the value it returned if any.
+	fn call(
+		&mut self,
+		f: String,
+		args: Vec<Expression>,
+		pos: &Range<usize>,
+	) -> Result<Option<Data>, Error> {
+		// functions such as `vector::push` change the variable passed first
+		let target = match args.first() {
+			Some(Expression::Identifier(ident)) if self.std.mutates(&f) => Some(ident.clone()),
+			_ if self.std.mutates(&f) => {
+				return Err(self.error(
 					"0004",
-					"function does not return a value",
-					"function does not a return a value",
+					"expected a variable",
+					&format!("`{f}` changes the vector passed first, so it must be a variable"),
 					pos,
-				),
-			};
+				))
+			}
+			_ => None,
+		};
+
+		let mut params = vec![];
+
+		for arg in args {
+			params.push(self.parse_expression(arg, None, pos)?);
 		}
-		let function = self.functions.get(&*f).unwrap().clone();
-		let datatype = &function.datatype;
 
-		if datatype.is_none() {
-			error(
-				self.name,
-				&self.file,
-				"0004",
-				"function does not return a value",
-				"function does not a return a value",
-				pos,
-			);
+		if self.limits.memory.is_some() {
+			self.reserve(self.std.grows(&f, &params), pos)?;
+		}
+
+		if let Some(target) = target {
+			let (data, vector) = self
+				.std
+				.call_mut(&f, pos, params)
+				.map_err(|error| error.with_trace(self.stack.clone()))?;
+
+			if let Some(mut variable) = self.variables.get(&target).cloned() {
+				variable.data = vector;
+				self.store(target, variable, pos)?;
+			}
+
+			return Ok(data);
 		}
 
-		if args.len() != function.params.len() {
-			error(
-				self.name,
-				&self.file,
+		if self.std.contains(&f) {
+			return self
+				.std
+				.call(&f, pos, params)
+				.map_err(|error| error.with_trace(self.stack.clone()));
+		}
+
+		let function = match self.functions.get(&f) {
+			Some(function) => function.clone(),
+			None => {
+				return Err(self.error(
+					"0004",
+					"function does not exist",
+					"tried to call a function which does not exist",
+					pos,
+				))
+			}
+		};
+
+		if params.len() != function.params.len() {
+			return Err(self.error(
 				"0004",
 				"not enough arguments were passed",
 				&format!(
 					"{} arguments were expected but {} were passed",
 					function.params.len(),
-					args.len()
+					params.len()
 				),
 				pos,
-			);
+			));
 		}
 
-		for (i, arg) in args.iter().enumerate() {
-			let param = function.params.get(i).unwrap();
-
-			let data = self.parse_expression(arg.to_owned(), None, pos);
+		if let Some(max) = self.limits.depth {
+			if self.stack.len() >= max {
+				return Err(self.error(
+					"0016",
+					"execution limit exceeded",
+					&format!("functions were called more than {max} deep"),
+					pos,
+				));
+			}
+		}
 
+		for (param, data) in function.params.iter().zip(params) {
 			if param.datatype != data.r#type() {
-				error(
-					self.name,
-					&self.file,
-					"E00011",
+				return Err(self.error(
+					"0011",
+					"incorrect data type",
 					&format!(
 						"mismatched data types expected {} found {}",
 						param.datatype, data
 					),
-					"incorrect data type",
 					pos,
-				)
+				));
 			}
 
-			self.variables.insert(
+			self.store(
 				param.name.clone(),
 				Variable::new(param.datatype.clone(), data),
-			);
+				pos,
+			)?;
+		}
+
+		if let Some(hook) = &self.hook {
+			hook.borrow_mut().enter(&function.name);
 		}
 
-		let mut stream = function.statements.into_iter().peekable();
-		loop {
-			if stream.peek().is_none() {
-				error(
-					self.name,
-					&self.file,
+		self.stack.push((function.name.clone(), pos.clone()));
+		self.block(function.statements)?;
+		self.stack.pop();
+
+		if let Some(hook) = &self.hook {
+			hook.borrow_mut().exit(&function.name);
+		}
+
+		Ok(self.returned.take())
+	}
+
+	fn parse_function(
+		&mut self,
+		f: String,
+		args: Vec<Expression>,
+		pos: &Range<usize>,
+	) -> Result<Data, Error> {
+		let returns = match self.std.signature(&f) {
+			Some(signature) => Some(&signature.returns),
+			None => self.functions.get(&f).map(|function| &function.datatype),
+		};
+
+		let datatype = match returns {
+			Some(None) => {
+				return Err(self.error(
+					"0004",
+					"function does not return a value",
+					"function does not a return a value",
+					pos,
+				))
+			}
+			Some(datatype) => datatype.clone(),
+			None => None,
+		};
+
+		let data = match self.call(f.clone(), args, pos)? {
+			Some(data) => data,
+			None => {
+				return Err(self.error(
 					"0004",
 					&format!("function {f} did not return a value"),
 					"expected function to return a value",
 					pos,
-				);
+				))
 			}
+		};
 
-			self.match_node(stream.next().unwrap());
-
-			if self.returned.is_some() {
-				let data = self.returned.clone().unwrap();
-				self.returned = None;
-
-				if data.r#type() != datatype.clone().unwrap() {
-					error(
-						self.name,
-						&self.file,
-						"0004",
-						&format!(
-							"mismatched data types expected {} found {}",
-							datatype.clone().unwrap(),
-							data
-						),
-						"incorrect data type",
-						pos,
-					);
-				}
-				break data;
+		if let Some(datatype) = datatype {
+			if data.r#type() != datatype {
+				return Err(self.error(
+					"0004",
+					&format!("mismatched data types expected {} found {}", datatype, data),
+					"incorrect data type",
+					pos,
+				));
 			}
 		}
+
+		Ok(data)
 	}
 
 	fn parse_expression(
@@ -449,94 +680,143 @@ impl<'a> Interpreter<'a> {
 		expr: Expression,
 		datatype: Option<DataType>,
 		pos: &Range<usize>,
-	) -> Data {
-		match expr {
-			Expression::BinaryOperation(lhs, op, rhs) => self.parse_binary_operation(*lhs, op, *rhs, pos),
+	) -> Result<Data, Error> {
+		Ok(match expr {
+			Expression::BinaryOperation(lhs, op, rhs) => {
+				self.parse_binary_operation(*lhs, op, *rhs, pos)?
+			}
+			Expression::UnaryOperation(op, expression) => {
+				match (op, self.parse_expression(*expression, None, pos)?) {
+					(Token::Subtraction, Data::Int(n)) => self.overflow(n.checked_neg(), pos)?,
+					(Token::Subtraction, Data::Float(f)) => Data::Float(-f),
+					(Token::Not, Data::Bool(b)) => Data::Bool(!b),
+					(op, data) => {
+						return Err(self.error(
+							"0002",
+							&format!("cannot apply `{}` to {data}", op.as_string()),
+							&format!(
+								"`{}` cannot be applied to a `{}`",
+								op.as_string(),
+								data.r#type()
+							),
+							pos,
+						))
+					}
+				}
+			}
 			Expression::Int(i) => Data::Int(i),
-			Expression::Identifier(i) => self.variables.get(&*i).unwrap().to_owned().data,
+			Expression::Float(f) => Data::Float(f),
+			Expression::Identifier(i) => self.variable(&i, pos)?.data,
 			Expression::Bool(b) => Data::Bool(b),
 			Expression::Str(s) => Data::Str(s),
-			Expression::FunctionCall(f, args) => self.parse_function(f, args, pos),
+			Expression::FunctionCall(f, args) => self.parse_function(f, args, pos)?,
 			Expression::Vector(vector, d) => {
 				let mut data = Vec::new();
-				let mut datatype = if d.is_some() { d } else { datatype };
+				let mut datatype = match (d, datatype) {
+					(Some(d), _) => Some(d),
+					(None, Some(DataType::Vector(d))) => Some(*d),
+					_ => None,
+				};
 				for expr in vector {
-					let d = self.parse_expression(expr, None, pos);
-
-					if datatype.is_none() {
-						datatype = Some(d.r#type());
-					} else if datatype.clone().unwrap() != d.r#type() {
-						error(
-							self.name,
-							&self.file,
-							"0004",
-							&format!(
-								"mismatched data types expected {} found {}",
-								datatype.unwrap(),
-								d
-							),
-							"incorrect data type",
-							pos,
-						);
+					let d = self.parse_expression(expr, None, pos)?;
+
+					match &datatype {
+						None => datatype = Some(d.r#type()),
+						Some(t) if *t != d.r#type() => {
+							return Err(self.error(
+								"0004",
+								&format!("mismatched data types expected {} found {}", t, d),
+								"incorrect data type",
+								pos,
+							))
+						}
+						_ => {}
 					}
 
 					data.push(d);
 				}
-				Data::Vector(data, datatype.unwrap())
+
+				match datatype {
+					Some(datatype) => Data::Vector(data, datatype),
+					None => {
+						return Err(self.error(
+							"0004",
+							"could not infer the type of the vector",
+							"declare the type of the variable holding this vector",
+							pos,
+						))
+					}
+				}
+			}
+			Expression::Block(statements, expression) => {
+				for node in statements {
+					self.match_node(node)?;
+				}
+
+				self.parse_expression(*expression, datatype, pos)?
+			}
+			Expression::VecSlice(ident, start, end) => {
+				let start = self.parse_expression(*start, None, pos)?;
+				let end = self.parse_expression(*end, None, pos)?;
+				let data = self.variable(&ident, pos)?.data;
+
+				self
+					.std
+					.call("vector::slice", pos, vec![data, start, end])
+					.map_err(|error| error.with_trace(self.stack.clone()))?
+					.unwrap()
 			}
 			Expression::VecIndex(ident, index) => {
-				let index = self.parse_expression(*index, None, pos);
-				let data = self.variables.get(&ident).unwrap().to_owned().data;
+				let index = self.parse_expression(*index, None, pos)?;
+				let data = self.variable(&ident, pos)?.data;
 
 				match data {
 					Data::Vector(vec, _) => match index {
 						Data::Int(i) => {
 							if i < 0 {
-								error(
-									self.name,
-									&self.file,
-									"E0004",
+								return Err(self.error(
+									"0006",
 									"index cannot be negative",
 									"index cannot be negative",
 									pos,
-								);
+								));
 							}
 							match vec.get(i as usize) {
 								Some(data) => data.to_owned(),
-								None => error(
-									self.name,
-									&self.file,
-									"E0004",
-									&format!(
-										"index out of bounds, index {} is out of bounds for vector of length {}",
-										i,
-										vec.len()
-									),
-									"index out of bounds",
-									pos,
-								),
+								None => {
+									return Err(self.error(
+										"0006",
+										&format!(
+											"index out of bounds, index {} is out of bounds for vector of length {}",
+											i,
+											vec.len()
+										),
+										"index out of bounds",
+										pos,
+									))
+								}
 							}
 						}
-						data => error(
-							self.name,
-							&self.file,
-							"E0004",
-							&format!("mismatched data types, expected `int` found {}", data),
-							"a value of type `int` was expected",
-							pos,
-						),
+						data => {
+							return Err(self.error(
+								"0002",
+								&format!("mismatched data types, expected `int` found {}", data),
+								"a value of type `int` was expected",
+								pos,
+							))
+						}
 					},
-					data => error(
-						self.name,
-						&self.file,
-						"0004",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0004",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				}
 			}
-		}
+		})
 	}
 
 	fn parse_binary_operation(
@@ -545,407 +825,767 @@ impl<'a> Interpreter<'a> {
 		op: Token,
 		rhs: Expression,
 		pos: &Range<usize>,
-	) -> Data {
-		let lhs = self.parse_expression(lhs, None, pos);
+	) -> Result<Data, Error> {
+		let lhs = self.parse_expression(lhs, None, pos)?;
 		let operator = op;
-		let rhs = self.parse_expression(rhs, None, pos);
-		match operator {
+		let rhs = self.parse_expression(rhs, None, pos)?;
+
+		if let Some((n, m)) = floats(&lhs, &rhs) {
+			return self.parse_float_operation(n, operator, m, pos);
+		}
+
+		Ok(match operator {
 			Token::Addition => match lhs {
 				Data::Str(str) => match rhs {
-					Data::Str(s) => Data::Str(str + &s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
-				},
-				Data::Int(n) => match rhs {
-					Data::Int(m) => Data::Int(n + m),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
-						pos,
-					),
+					Data::Str(s) => {
+						self.reserve(str.len() + s.len(), pos)?;
+						Data::Str(str + &s)
+					}
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
-				data => error(
-					self.name,
-					&self.file,
-					"0002",
-					&format!(
-						"mismatched data types, expected `String` or `int` found {}",
-						data
-					),
-					"a value of type `String` or `int` was expected",
-					pos,
-				),
+				Data::Int(n) => match rhs {
+					Data::Int(m) => self.overflow(n.checked_add(m), pos)?,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				data => {
+					return Err(self.error(
+						"0002",
+						&format!(
+							"mismatched data types, expected `String` or `int` found {}",
+							data
+						),
+						"a value of type `String` or `int` was expected",
+						pos,
+					))
+				}
 			},
 			Token::Subtraction => match lhs {
 				Data::Int(n) => match rhs {
-					Data::Int(m) => Data::Int(n - m),
-					data => error(
-						self.name,
-						&self.file,
+					Data::Int(m) => self.overflow(n.checked_sub(m), pos)?,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				data => {
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `int` found {}", data),
 						"a value of type `int` was expected",
 						pos,
-					),
-				},
-				data => error(
-					self.name,
-					&self.file,
-					"0002",
-					&format!("mismatched data types, expected `int` found {}", data),
-					"a value of type `int` was expected",
-					pos,
-				),
+					))
+				}
 			},
 			Token::Multiplication => match lhs {
 				Data::Int(n) => match rhs {
-					Data::Int(m) => Data::Int(n * m),
-					data => error(
-						self.name,
-						&self.file,
+					Data::Int(m) => self.overflow(n.checked_mul(m), pos)?,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				data => {
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `int` found {}", data),
 						"a value of type `int` was expected",
 						pos,
-					),
-				},
-				data => error(
-					self.name,
-					&self.file,
-					"0002",
-					&format!("mismatched data types, expected `int` found {}", data),
-					"a value of type `int` was expected",
-					pos,
-				),
+					))
+				}
 			},
 			Token::Division => match lhs {
 				Data::Int(n) => match rhs {
-					Data::Int(m) => Data::Int(n / m),
-					data => error(
-						self.name,
-						&self.file,
+					Data::Int(0) => {
+						return Err(self.error(
+							"0007",
+							"attempt to divide by zero",
+							"the divisor of this division is zero",
+							pos,
+						))
+					}
+					Data::Int(m) => self.overflow(n.checked_div(m), pos)?,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				data => {
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `int` found {}", data),
 						"a value of type `int` was expected",
 						pos,
-					),
-				},
-				data => error(
-					self.name,
-					&self.file,
-					"0002",
-					&format!("mismatched data types, expected `int` found {}", data),
-					"a value of type `int` was expected",
-					pos,
-				),
+					))
+				}
 			},
 			Token::Power => match lhs {
 				Data::Int(n) => match rhs {
-					Data::Int(m) => Data::Int(n.pow(m as u32)),
-					data => error(
-						self.name,
-						&self.file,
+					Data::Int(m) if m < 0 => {
+						return Err(self.error(
+							"0007",
+							"negative exponent",
+							"an `int` cannot be raised to a negative power",
+							pos,
+						))
+					}
+					Data::Int(m) => self.overflow(n.checked_pow(m as u32), pos)?,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				data => {
+					return Err(self.error(
 						"0002",
 						&format!("mismatched data types, expected `int` found {}", data),
 						"a value of type `int` was expected",
 						pos,
-					),
-				},
-				data => error(
-					self.name,
-					&self.file,
-					"0002",
-					&format!("mismatched data types, expected `int` found {}", data),
-					"a value of type `int` was expected",
-					pos,
-				),
+					))
+				}
 			},
 			Token::IsEqual => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str == s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n == m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(b == d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 == v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			Token::IsNotEqual => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str != s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n != m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(b != d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 != v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			Token::IsGreater => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str > s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n > m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(b & !d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 > v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			Token::IsLesser => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str < s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n < m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(!b & d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 < v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			Token::IsGreaterEqual => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str >= s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n >= m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(b >= d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 >= v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			Token::IsLesserEqual => match lhs {
 				Data::Str(str) => match rhs {
 					Data::Str(s) => Data::Bool(str <= s),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `String` found {}", data),
-						"a value of type `String` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `String` found {}", data),
+							"a value of type `String` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Int(n) => match rhs {
 					Data::Int(m) => Data::Bool(n <= m),
-					data => error(
-						self.name,
-						&self.file,
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `int` found {}", data),
+							"a value of type `int` was expected",
+							pos,
+						))
+					}
+				},
+				Data::Float(_) => {
+					return Err(self.error(
 						"0002",
-						&format!("mismatched data types, expected `int` found {}", data),
-						"a value of type `int` was expected",
+						&format!("mismatched data types, expected `float` found {}", rhs),
+						"a value of type `float` was expected",
 						pos,
-					),
-				},
+					))
+				}
 				Data::Bool(b) => match rhs {
 					Data::Bool(d) => Data::Bool(b <= d),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `bool` found {}", data),
-						"a value of type `bool` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `bool` found {}", data),
+							"a value of type `bool` was expected",
+							pos,
+						))
+					}
 				},
 				Data::Vector(v1, _) => match rhs {
 					Data::Vector(v2, _) => Data::Bool(v1 <= v2),
-					data => error(
-						self.name,
-						&self.file,
-						"0002",
-						&format!("mismatched data types, expected `vector` found {}", data),
-						"a value of type `vector` was expected",
-						pos,
-					),
+					data => {
+						return Err(self.error(
+							"0002",
+							&format!("mismatched data types, expected `vector` found {}", data),
+							"a value of type `vector` was expected",
+							pos,
+						))
+					}
 				},
 			},
 			_ => unreachable!(),
+		})
+	}
+
+	fn parse_float_operation(
+		&self,
+		n: f64,
+		operator: Token,
+		m: f64,
+		pos: &Range<usize>,
+	) -> Result<Data, Error> {
+		Ok(match operator {
+			Token::Addition => Data::Float(n + m),
+			Token::Subtraction => Data::Float(n - m),
+			Token::Multiplication => Data::Float(n * m),
+			Token::Division if m == 0.0 => {
+				return Err(self.error(
+					"0007",
+					"attempt to divide by zero",
+					"the divisor of this division is zero",
+					pos,
+				))
+			}
+			Token::Division => Data::Float(n / m),
+			Token::Power => Data::Float(n.powf(m)),
+			Token::IsEqual => Data::Bool(n == m),
+			Token::IsNotEqual => Data::Bool(n != m),
+			Token::IsGreater => Data::Bool(n > m),
+			Token::IsLesser => Data::Bool(n < m),
+			Token::IsGreaterEqual => Data::Bool(n >= m),
+			Token::IsLesserEqual => Data::Bool(n <= m),
+			_ => unreachable!(),
+		})
+	}
+
+	fn overflow(&self, data: Option<i32>, pos: &Range<usize>) -> Result<Data, Error> {
+		match data {
+			Some(i) => Ok(Data::Int(i)),
+			None => Err(self.error(
+				"0007",
+				"integer overflow",
+				"the result of this operation does not fit in an `int`",
+				pos,
+			)),
+		}
+	}
+}
+
+/// Both operands as floats if one is a float and the other a number, an
+/// `int` mixed with a `float` is widened to a `float`.
+fn floats(lhs: &Data, rhs: &Data) -> Option<(f64, f64)> {
+	match (lhs, rhs) {
+		(Data::Float(n), Data::Float(m)) => Some((*n, *m)),
+		(Data::Float(n), Data::Int(m)) => Some((*n, *m as f64)),
+		(Data::Int(n), Data::Float(m)) => Some((*n as f64, *m)),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Interpreter;
+	use crate::{
+		data::{Data, Value},
+		error::Error,
+		lexer::Lexer,
+		parser::Parser,
+		sandbox::{Cancel, Capabilities, Limits},
+		standardlibrary::Signature,
+	};
+	use std::{cell::RefCell, rc::Rc, time::Duration};
+
+	fn run(interpreter: &mut Interpreter, file: &str) -> Result<(), Error> {
+		let tokens = Lexer::new(file).run().unwrap().to_vec();
+		let (ast, errors) = Parser::new().run(tokens);
+		assert!(errors.is_empty(), "{errors:?}");
+
+		interpreter.run(ast)
+	}
+
+	/// Runs `file` and returns what it printed.
+	fn output(interpreter: &mut Interpreter, file: &str) -> String {
+		let output = Rc::new(RefCell::new(vec![]));
+		interpreter.set_stdout(output.clone());
+		run(interpreter, file).unwrap();
+
+		let output = output.take();
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn registered_functions() {
+		let mut interpreter = Interpreter::new();
+		interpreter.register_fn(
+			"host::longer",
+			Signature::new(
+				vec![i64::datatype(), String::datatype()],
+				Some(bool::datatype()),
+			),
+			|params| {
+				let [n, s] = <[Data; 2]>::try_from(params).unwrap();
+				let (n, s) = (i64::from_data(n)?, String::from_data(s)?);
+
+				(s.chars().count() as i64 > n).into_data().map(Some)
+			},
+		);
+		assert!(interpreter.library().names().contains(&"host::longer"));
+
+		let printed = output(
+			&mut interpreter,
+			r#"let b: bool = host::longer(2, "abc"); println(b, host::longer(5, "abc"));"#,
+		);
+		assert_eq!(printed, "true\nfalse\n");
+
+		let error = run(&mut interpreter, r#"println(host::longer("abc", 2));"#).unwrap_err();
+		assert_eq!(error.code, "0011");
+		assert_eq!(
+			error.note,
+			"argument 1 of `host::longer` expected `int` found str"
+		);
+
+		let error = run(&mut interpreter, "println(host::longer(1));").unwrap_err();
+		assert_eq!(error.code, "0004");
+	}
+
+	#[test]
+	fn registered_functions_without_a_value() {
+		let mut interpreter = Interpreter::new();
+		interpreter.register_fn("host::nothing", Signature::new(vec![], None), |_| Ok(None));
+
+		run(&mut interpreter, "host::nothing();").unwrap();
+
+		let error = run(&mut interpreter, "let a: int = host::nothing();").unwrap_err();
+		assert_eq!(error.message, "function does not return a value");
+	}
+
+	#[test]
+	fn conversions() {
+		assert_eq!(i64::from_data(Data::Int(3)), Ok(3));
+		assert!(i64::from_data(Data::Str("3".to_string())).is_err());
+		assert!(i64::MAX.into_data().is_err());
+
+		let strs = vec!["a".to_string(), "b".to_string()];
+		assert_eq!(
+			Vec::<String>::from_data(strs.clone().into_data().unwrap()),
+			Ok(strs)
+		);
+	}
+
+	#[test]
+	fn args() {
+		let mut interpreter = Interpreter::new();
+		assert_eq!(output(&mut interpreter, "println(args());"), "[]\n");
+
+		interpreter.set_args(vec!["a".to_string(), "b".to_string()]);
+		assert_eq!(output(&mut interpreter, "println(args());"), "[a, b]\n");
+	}
+
+	#[test]
+	fn runaway_loops_are_stopped() {
+		let limits = Limits {
+			instructions: Some(1000),
+			..Default::default()
+		};
+		let mut interpreter = Interpreter::sandboxed(Capabilities::default(), limits);
+
+		let error = run(&mut interpreter, "loop {}").unwrap_err();
+		assert_eq!(error.code, "0016");
+		assert_eq!(error.note, "the program ran more than 1000 instructions");
+	}
+
+	#[test]
+	fn values_over_the_memory_limit_are_not_built() {
+		let limits = Limits {
+			memory: Some(1000),
+			..Default::default()
+		};
+		let interpreter = || Interpreter::sandboxed(Capabilities::default(), limits.clone());
+
+		for file in [
+			"let v: vec<int> = vector::range(0, 2000000000);",
+			r#"let s: str = string::repeat("abc", 2000000000);"#,
+			r#"let s: str = string::repeat("a", 600); let t: str = s + s;"#,
+			"let v: vec<int> = vector::range(0, 12); let w: vec<int> = vector::concat(v, v);",
+			r#"let v: vec<str> = []; vector::push(v, string::repeat("a", 960));"#,
+		] {
+			let error = run(&mut interpreter(), file).unwrap_err();
+			assert_eq!(error.code, "0016", "{file}");
 		}
+
+		let printed = output(
+			&mut interpreter(),
+			"let v: vec<int> = vector::range(0, 3); vector::push(v, 3); println(v);",
+		);
+		assert_eq!(printed, "[0, 1, 2, 3]\n");
+	}
+
+	#[test]
+	fn deep_recursion_is_stopped() {
+		let limits = Limits {
+			depth: Some(50),
+			..Default::default()
+		};
+		let mut interpreter = Interpreter::sandboxed(Capabilities::default(), limits);
+		let f = "fn f(n: int) -> int { if n == 0 { return 0; } return f(n - 1) + 1; }";
+
+		assert_eq!(
+			output(&mut interpreter, &format!("{f} println(f(49));")),
+			"49\n"
+		);
+
+		let error = run(&mut interpreter, "println(f(50));").unwrap_err();
+		assert_eq!(error.code, "0016");
+		assert_eq!(error.note, "functions were called more than 50 deep");
+		assert!(interpreter.stack().is_empty());
+	}
+
+	#[test]
+	fn limits_are_counted_again_every_run() {
+		let limits = Limits {
+			instructions: Some(100),
+			time: Some(Duration::from_millis(50)),
+			..Default::default()
+		};
+		let mut interpreter = Interpreter::sandboxed(Capabilities::default(), limits);
+		let file = "let i: int = 0; loop { if i == 30 { break; } i = i + 1; }";
+
+		run(&mut interpreter, file).unwrap();
+		std::thread::sleep(Duration::from_millis(60));
+		run(&mut interpreter, file).unwrap();
+		run(&mut interpreter, file).unwrap();
+	}
+
+	#[test]
+	fn loops_past_the_deadline_are_stopped() {
+		let limits = Limits {
+			time: Some(Duration::from_millis(10)),
+			..Default::default()
+		};
+		let mut interpreter = Interpreter::sandboxed(Capabilities::default(), limits);
+
+		let error = run(&mut interpreter, "let i: int = 0; loop { i = 0; }").unwrap_err();
+		assert_eq!(error.code, "0016");
+	}
+
+	#[test]
+	fn cancelled_programs_are_stopped() {
+		let mut interpreter = Interpreter::new();
+		let cancel = Cancel::default();
+		interpreter.set_cancel(cancel.clone());
+		cancel.cancel();
+
+		let error = run(&mut interpreter, "loop {}").unwrap_err();
+		assert_eq!(error.code, "0017");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn spawned_processes_print_to_the_streams() {
+		let mut interpreter = Interpreter::new();
+		let file = r#"process::spawn("sh", ["-c", "echo out; echo err >&2"]);"#;
+
+		let stderr = Rc::new(RefCell::new(vec![]));
+		interpreter.set_stderr(stderr.clone());
+		assert_eq!(output(&mut interpreter, file), "out\n");
+		assert_eq!(stderr.take(), b"err\n");
+
+		let capabilities = Capabilities {
+			stdout: false,
+			..Default::default()
+		};
+		let mut interpreter = Interpreter::sandboxed(capabilities, Limits::default());
+		interpreter.set_stderr(stderr.clone());
+		assert_eq!(output(&mut interpreter, file), "");
+		assert_eq!(stderr.take(), b"err\n");
+	}
+
+	#[test]
+	fn one_code_per_error() {
+		let mut interpreter = Interpreter::new();
+		let codes = [
+			"let v: vec<int> = [1]; println(v[3]);",
+			"let v: vec<int> = [1]; println(vector::slice(v, 0, 3));",
+			"let v: vec<int> = []; vector::pop(v);",
+			"println(2147483647 + 1);",
+			"println(math::abs(-2147483647 - 1));",
+		]
+		.map(|file| run(&mut interpreter, file).unwrap_err().code);
+
+		assert_eq!(codes, ["0006", "0006", "0006", "0007", "0007"]);
 	}
 }
@@ -1,433 +1,165 @@
+use std::{iter::Peekable, ops::Range, str::CharIndices};
+
+use unicode_xid::UnicodeXID;
+
 use crate::{
 	data::DataType,
-	error::error,
-	token::{Token, Tokens},
+	error::Error,
+	token::{Span, Token, Tokens},
 };
 
 #[derive(Debug, Clone)]
 pub struct Lexer<'a> {
-	name: &'a str,
 	file: &'a str,
-	at: usize,
-	tokens: Vec<(Token, usize)>,
+	chars: Peekable<CharIndices<'a>>,
+	line: usize,
+	line_start: usize,
+	scanned: usize,
+	tokens: Tokens,
+	comments: Vec<(String, Span)>,
 }
 
 impl<'a> Lexer<'a> {
-	pub fn new(name: &'a str, file: &'a str) -> Self {
+	pub fn new(file: &'a str) -> Self {
 		Self {
-			name,
 			file,
-			at: 0,
+			chars: file.char_indices().peekable(),
+			line: 1,
+			line_start: 0,
+			scanned: 0,
 			tokens: vec![],
+			comments: vec![],
 		}
 	}
 
-	pub fn run(&mut self) -> Option<&Tokens> {
-		let file = self.file.split("").collect::<Vec<&'a str>>();
-
-		let mut stream = file.iter().peekable();
-
-		let size = self.file.len();
-		loop {
-			let mut token = String::new();
-
-			let ch = stream.peek();
-			self.at += 1;
-
-			if ch.is_none() {
-				if self.at > size {
-					break;
-				}
-				stream.next();
-				continue;
-			}
-
-			let ch = if ch?.is_empty() {
-				stream.next();
-				continue;
-			} else {
-				stream.next()?.chars().next()?
-			};
-
+	pub fn run(&mut self) -> Result<&Tokens, Error> {
+		while let Some((start, ch)) = self.chars.next() {
 			if ch.is_whitespace() {
 				continue;
 			}
 
-			if ch.is_alphabetic() {
-				token.push(ch);
-				loop {
-					let ch = stream.peek();
-					self.at += 1;
-					if ch.is_none() || ch?.is_empty() {
-						if self.at > size {
-							break;
-						}
-						stream.next();
-						continue;
-					}
+			if ch.is_xid_start() || ch == '_' {
+				let mut end = self.take_while(|ch| ch.is_xid_continue());
 
-					if !ch?.chars().next()?.is_alphabetic() {
-						self.at -= 1;
-						break;
-					}
-
-					let ch = if ch?.is_empty() {
-						stream.next();
-						continue;
-					} else {
-						stream.next()?.chars().next()?
-					};
-
-					token.push(ch);
+				// paths such as `string::len` name functions in standard library modules
+				while self.file[end..].starts_with("::")
+					&& self.file[end + 2..].starts_with(|ch: char| ch.is_xid_start() || ch == '_')
+				{
+					self.chars.nth(1);
+					end = self.take_while(|ch| ch.is_xid_continue());
 				}
-			} else if ch == '"' {
-				loop {
-					let ch = stream.peek();
-					self.at += 1;
-
-					if ch.is_none() {
-						if self.at > size {
-							break;
-						}
-						stream.next();
-						continue;
-					}
-
-					if ch?.starts_with('\"') {
-						self.at -= 1;
-						stream.next();
-						break;
-					}
 
-					let ch = if ch?.is_empty() {
-						stream.next();
-						continue;
-					} else {
-						stream.next()?.chars().next()?
-					};
-
-					token.push(ch);
-				}
-				let t = Token::Str(token);
-				let size = t.len();
-				self.tokens.push((t, self.at - size));
-				continue;
-			} else if ch.is_numeric() {
-				token.push(ch);
-				loop {
-					let ch = stream.peek();
-					self.at += 1;
+				let t = match &self.file[start..end] {
+					"let" => Token::Let,
+					"if" => Token::If,
+					"else" => Token::Else,
+					"loop" => Token::Loop,
+					"fn" => Token::Fn,
+					"exit" => Token::Exit,
+					"break" => Token::Break,
+					"return" => Token::Return,
+					"true" => Token::Bool(true),
+					"false" => Token::Bool(false),
+					ident => {
+						let declaration = matches!(self.tokens.last(), Some((Token::Fn, _)));
 
-					if ch.is_none() || ch?.is_empty() {
-						if self.at > size {
-							break;
+						if declaration || self.next_is('(') {
+							Token::FunctionName(ident.to_string())
+						} else {
+							Token::Identifier(ident.to_string())
 						}
-						stream.next();
-						continue;
 					}
-
-					if !ch?.chars().next()?.is_numeric() {
-						self.at -= 1;
-						break;
-					}
-
-					let ch = if ch?.is_empty() {
-						stream.next();
-						continue;
-					} else {
-						stream.next()?.chars().next()?
-					};
-
-					token.push(ch);
-				}
-				let t = Token::Int(token.parse::<i32>().unwrap());
-				let size = t.len();
-				self.tokens.push((t, self.at - size));
-				continue;
-			} else if ch == ':' {
+				};
+				self.push(t, start);
+			} else if ch == '"' {
+				let mut string = String::new();
 				loop {
-					let ch = stream.peek();
-					self.at += 1;
-
-					if ch.is_none() {
-						if self.at > size {
-							break;
+					match self.chars.next() {
+						Some((_, '"')) => break,
+						Some((i, '\\')) => string.push(self.escape(i)?),
+						Some((_, ch)) => string.push(ch),
+						None => {
+							return Err(Error::new(
+								"0001",
+								"unterminated string",
+								"string is never closed",
+								start..self.file.len(),
+							))
 						}
-						stream.next();
-						continue;
-					}
-					let char = ch?.chars().next()?;
-
-					if char.is_whitespace() {
-						stream.next();
-						continue;
 					}
-
-					if !char.is_alphabetic() && char != '<' && char != '>' {
-						self.at -= 1;
-						break;
-					}
-
-					let ch = if ch?.is_empty() {
-						stream.next();
-						continue;
-					} else {
-						stream.next()?.chars().next()?
-					};
-
-					token.push(ch);
 				}
-
-				fn match_str(token: &str, name: &str, file: &str, at: usize) -> DataType {
-					match token {
-						"str" => DataType::Str,
-						"int" => DataType::Int,
-						"bool" => DataType::Bool,
-						t => {
-							if t.starts_with("vec") {
-								let chars = t.chars();
-								let mut chars = chars.skip(3);
-								let ch = chars.next();
-
-								if ch.unwrap() != '<' {
-									error(
-										name,
-										file,
-										"0001",
-										&format!("expected `<` found `{t}`"),
-										&format!("token `{t}` was not expected here"),
-										&(at..at + 1),
-									)
-								}
-
-								let mut chars = chars.collect::<String>();
-
-								if chars.pop().unwrap() != '>' {
-									error(
-										name,
-										file,
-										"0001",
-										&format!("expected `<` found `{t}`"),
-										&format!("token `{t}` was not expected here"),
-										&(at..at + 1),
-									)
-								}
-
-								return DataType::Vector(Box::new(match_str(&chars, name, file, at)));
-							}
-							error(
-								name,
-								file,
+				self.push(Token::Str(string), start);
+			} else if ch.is_ascii_digit() {
+				let mut end = self.take_while(|ch| ch.is_ascii_digit());
+
+				// a `.` followed by a digit makes a float, `1..3` is a range of ints
+				if self.file[end..].starts_with('.')
+					&& self.file[end + 1..].starts_with(|ch: char| ch.is_ascii_digit())
+				{
+					self.chars.next();
+					end = self.take_while(|ch| ch.is_ascii_digit());
+
+					let t = match self.file[start..end].parse::<f64>() {
+						Ok(f) => Token::Float(f),
+						Err(_) => {
+							return Err(Error::new(
 								"0001",
-								&format!("expected datatype found `{t}`"),
-								&format!("token `{t}` was not expected here"),
-								&(at..at + 1),
-							)
+								&format!("`{}` is not a valid float", &self.file[start..end]),
+								"float could not be read",
+								start..end,
+							))
 						}
-					}
+					};
+					self.push(t, start);
+					continue;
 				}
 
-				let t = Token::DataType(match_str(&token, self.name, self.file, self.at));
-
-				let size = t.len();
-
-				self.tokens.push((t, self.at - size));
-
-				continue;
-			} else if ['+', '-', '*', '/', '^', '!', '=', '>', '<'].contains(&ch) {
+				let t = match self.file[start..end].parse::<i32>() {
+					Ok(i) => Token::Int(i),
+					Err(_) => {
+						return Err(Error::new(
+							"0001",
+							&format!("`{}` is not a valid integer", &self.file[start..end]),
+							"integer is too large",
+							start..end,
+						))
+					}
+				};
+				self.push(t, start);
+			} else if ch == ':' {
+				self.datatype()?;
+			} else {
 				let t = match ch {
 					'+' => Token::Addition,
 					'-' => {
-						let ch = stream.peek();
-						self.at += 1;
-
-						if ch.is_none() {
-							if self.at > size {
-								break;
-							}
-							stream.next();
+						if self.next_is('>') {
+							self.chars.next();
+							self.datatype()?;
 							continue;
 						}
-
-						let ch = if ch?.is_empty() {
-							stream.next();
-							continue;
-						} else {
-							stream.peek()?.chars().next()?
-						};
-
-						if ch == '>' {
-							stream.next();
-							loop {
-								let ch = stream.peek();
-								self.at += 1;
-
-								if ch.is_none() {
-									if self.at > size {
-										break;
-									}
-									stream.next();
-									continue;
-								}
-
-								if ch?.chars().next()?.is_whitespace() {
-									stream.next();
-									continue;
-								}
-
-								if !ch?.chars().next()?.is_alphabetic() {
-									self.at -= 1;
-									break;
-								}
-
-								let ch = if ch?.is_empty() {
-									stream.next();
-									continue;
-								} else {
-									stream.next()?.chars().next()?
-								};
-
-								token.push(ch);
-							}
-
-							match token.as_str() {
-								"str" => Token::DataType(DataType::Str),
-								"int" => Token::DataType(DataType::Int),
-								"bool" => Token::DataType(DataType::Bool),
-								_ => error(
-									self.name,
-									self.file,
-									"0001",
-									&format!("expected datatype found `{ch}`"),
-									&format!("token `{ch}` was not expected here"),
-									&(self.at..self.at + 1),
-								),
-							}
-						} else {
-							self.at -= 1;
-							Token::Subtraction
-						}
+						Token::Subtraction
 					}
 					'*' => Token::Multiplication,
+					'/' if self.next_is('/') => {
+						let end = self.take_while(|ch| ch != '\n');
+						let span = self.span(start, end);
+						self
+							.comments
+							.push((self.file[start..end].to_string(), span));
+						continue;
+					}
 					'/' => Token::Division,
 					'^' => Token::Power,
-					'!' => {
-						let ch = stream.peek();
-						self.at += 1;
-
-						if ch.is_none() {
-							if self.at > size {
-								break;
-							}
-							stream.next();
-							continue;
-						}
-
-						let ch = if ch?.is_empty() {
-							stream.next();
-							continue;
-						} else {
-							stream.peek()?.chars().next()?
-						};
-
-						if ch == '=' {
-							stream.next();
-							Token::IsNotEqual
-						} else {
-							self.at -= 1;
-							Token::Not
-						}
-					}
-					'=' => {
-						let ch = stream.peek();
-						self.at += 1;
-
-						if ch.is_none() {
-							if self.at > size {
-								break;
-							}
-							stream.next();
-							continue;
-						}
-
-						let ch = if ch?.is_empty() {
-							stream.next();
-							continue;
-						} else {
-							stream.peek()?.chars().next()?
-						};
-
-						if ch == '=' {
-							stream.next();
-							Token::IsEqual
-						} else {
-							self.at -= 1;
-							Token::Equal
-						}
-					}
-					'>' => {
-						let ch = stream.peek();
-						self.at += 1;
-
-						if ch.is_none() {
-							if self.at > size {
-								break;
-							}
-							stream.next();
-							continue;
-						}
-
-						let ch = if ch?.is_empty() {
-							stream.next();
-							continue;
-						} else {
-							stream.peek()?.chars().next()?
-						};
-
-						if ch == '=' {
-							stream.next();
-							Token::IsGreaterEqual
-						} else {
-							self.at -= 1;
-							Token::IsGreater
-						}
-					}
-					'<' => {
-						let ch = stream.peek();
-						self.at += 1;
-
-						if ch.is_none() {
-							if self.at > size {
-								break;
-							}
-							stream.next();
-							continue;
-						}
-
-						let ch = if ch?.is_empty() {
-							stream.next();
-							continue;
-						} else {
-							stream.peek()?.chars().next()?
-						};
-
-						if ch == '=' {
-							stream.next();
-							Token::IsLesserEqual
-						} else {
-							self.at -= 1;
-							Token::IsLesser
-						}
-					}
-					_ => unimplemented!(),
-				};
-				let size = t.len();
-				self.tokens.push((t, self.at - size));
-				continue;
-			} else {
-				let t = match ch {
+					'!' => self.either('=', Token::IsNotEqual, Token::Not),
+					'=' => self.either('=', Token::IsEqual, Token::Equal),
+					'>' => self.either('=', Token::IsGreaterEqual, Token::IsGreater),
+					'<' => self.either('=', Token::IsLesserEqual, Token::IsLesser),
 					';' => Token::Semicolon,
 					',' => Token::Comma,
+					'.' if self.next_is('.') => {
+						self.chars.next();
+						Token::Range
+					}
 					')' => Token::RParen,
 					'(' => Token::LParen,
 					'}' => Token::RCurly,
@@ -435,50 +167,265 @@ impl<'a> Lexer<'a> {
 					']' => Token::RSquare,
 					'[' => Token::LSquare,
 					_ => {
-						self.at -= 2;
-						error(
-							self.name,
-							self.file,
+						return Err(Error::new(
 							"0001",
 							&format!("character `{ch}` was not expected here"),
 							&format!("character `{ch}` was not expected here"),
-							&(self.at..self.at + 1),
-						)
+							start..start + ch.len_utf8(),
+						))
 					}
 				};
-				let size = t.len();
-				self.tokens.push((t, self.at - size))
+				self.push(t, start);
 			}
+		}
 
-			if !token.is_empty() {
-				let t = match token.as_str() {
-					"let" => Token::Let,
-					"if" => Token::If,
-					"else" => Token::Else,
-					"loop" => Token::Loop,
-					"fn" => Token::Fn,
-					"exit" => Token::Exit,
-					"break" => Token::Break,
-					"return" => Token::Return,
-					"true" => Token::Bool(true),
-					"false" => Token::Bool(false),
-					_ => {
-						if self.tokens.last().is_some() && self.tokens.last()?.0 == Token::Fn {
-							Token::FunctionName(token)
-						} else {
-							let mut cloned_stream = stream.clone();
-							if cloned_stream.peek().is_some() && cloned_stream.next()?.starts_with('(') {
-								Token::FunctionName(token)
-							} else {
-								Token::Identifier(token)
-							}
-						}
+		Ok(&self.tokens)
+	}
+
+	/// The `//` comments skipped by [`Lexer::run`], including the slashes.
+	pub fn comments(&self) -> &[(String, Span)] {
+		&self.comments
+	}
+
+	/// Byte offset of the next unread character.
+	fn offset(&mut self) -> usize {
+		match self.chars.peek() {
+			Some((i, _)) => *i,
+			None => self.file.len(),
+		}
+	}
+
+	fn next_is(&mut self, ch: char) -> bool {
+		matches!(self.chars.peek(), Some((_, c)) if *c == ch)
+	}
+
+	/// Consumes characters while `f` holds and returns the offset after them.
+	fn take_while(&mut self, f: impl Fn(char) -> bool) -> usize {
+		while self.chars.next_if(|(_, ch)| f(*ch)).is_some() {}
+
+		self.offset()
+	}
+
+	/// Lexes the escape sequence following the `\` at `start` in a string.
+	/// Only `\n`, `\t`, `\r`, `\0`, `\"`, `\\` and `\u{...}` are escapes,
+	/// any other backslash is kept as it is.
+	fn escape(&mut self, start: usize) -> Result<char, Error> {
+		let offset = self.offset();
+		let escaped = match self.chars.peek() {
+			Some((_, 'n')) => '\n',
+			Some((_, 't')) => '\t',
+			Some((_, 'r')) => '\r',
+			Some((_, '0')) => '\0',
+			Some((_, '"')) => '"',
+			Some((_, '\\')) => '\\',
+			Some((_, 'u')) if self.file[offset..].starts_with("u{") => {
+				self.chars.next();
+				self.chars.next();
+				let digits = self.offset();
+				let end = self.take_while(|ch| ch.is_ascii_hexdigit());
+
+				let ch = u32::from_str_radix(&self.file[digits..end], 16)
+					.ok()
+					.and_then(char::from_u32);
+
+				return match ch {
+					Some(ch) if self.next_is('}') => {
+						self.chars.next();
+						Ok(ch)
 					}
+					_ => Err(Error::new(
+						"0001",
+						"invalid unicode escape",
+						"expected a unicode scalar value such as `\\u{1F600}`",
+						start..self.offset(),
+					)),
 				};
-				let size = t.len();
-				self.tokens.push((t, self.at - size))
 			}
+			_ => return Ok('\\'),
+		};
+		self.chars.next();
+
+		Ok(escaped)
+	}
+
+	fn either(&mut self, next: char, matched: Token, otherwise: Token) -> Token {
+		if self.next_is(next) {
+			self.chars.next();
+			matched
+		} else {
+			otherwise
 		}
-		Some(&self.tokens)
+	}
+
+	/// Lexes the data type following a `:` or `->`.
+	fn datatype(&mut self) -> Result<(), Error> {
+		self.take_while(|ch| ch.is_whitespace());
+
+		let start = self.offset();
+		let end = self.take_while(|ch| ch.is_alphabetic() || ch == '<' || ch == '>');
+
+		let datatype = self.match_datatype(&self.file[start..end], start..end)?;
+		self.push(Token::DataType(datatype), start);
+
+		Ok(())
+	}
+
+	fn match_datatype(&self, token: &str, range: Range<usize>) -> Result<DataType, Error> {
+		Ok(match token {
+			"str" => DataType::Str,
+			"int" => DataType::Int,
+			"float" => DataType::Float,
+			"bool" => DataType::Bool,
+			t => {
+				if let Some(inner) = t.strip_prefix("vec") {
+					if !inner.starts_with('<') || !inner.ends_with('>') || inner.len() < 2 {
+						return Err(Error::new(
+							"0001",
+							&format!("expected `vec<T>` found `{t}`"),
+							&format!("token `{t}` was not expected here"),
+							range.clone(),
+						));
+					}
+
+					return Ok(DataType::Vector(Box::new(
+						self.match_datatype(&inner[1..inner.len() - 1], range)?,
+					)));
+				}
+				return Err(Error::new(
+					"0001",
+					&format!("expected datatype found `{t}`"),
+					&format!("token `{t}` was not expected here"),
+					range,
+				));
+			}
+		})
+	}
+
+	fn push(&mut self, token: Token, start: usize) {
+		let end = self.offset();
+		let span = self.span(start, end);
+
+		self.tokens.push((token, span));
+	}
+
+	fn span(&mut self, start: usize, end: usize) -> Span {
+		for (i, ch) in self.file[self.scanned..start].char_indices() {
+			if ch == '\n' {
+				self.line += 1;
+				self.line_start = self.scanned + i + 1;
+			}
+		}
+		self.scanned = start;
+
+		let column = self.file[self.line_start..start].chars().count() + 1;
+
+		Span::new(start..end, self.line, column)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Lexer;
+	use crate::token::{Span, Token};
+
+	fn lex(file: &str) -> Vec<(Token, Span)> {
+		Lexer::new(file).run().unwrap().to_vec()
+	}
+
+	#[test]
+	fn spans_are_byte_offsets() {
+		let file = "let s: str = \"😀 你好\"; let n: int = 1;";
+		let tokens = lex(file);
+
+		let (token, span) = &tokens[4];
+		assert_eq!(*token, Token::Str("😀 你好".to_string()));
+		assert_eq!(&file[span.range.clone()], "\"😀 你好\"");
+
+		let (token, span) = &tokens[10];
+		assert_eq!(*token, Token::Int(1));
+		assert_eq!(&file[span.range.clone()], "1");
+		assert_eq!(span.column, 35);
+	}
+
+	#[test]
+	fn unknown_escapes_are_kept() {
+		let tokens = lex(r#"print("C:\dir\n\q", "\u");"#);
+
+		assert_eq!(tokens[2].0, Token::Str("C:\\dir\n\\q".to_string()));
+		assert_eq!(tokens[4].0, Token::Str("\\u".to_string()));
+	}
+
+	#[test]
+	fn unicode_identifiers() {
+		let tokens = lex("let 名前: str = \"\"; println(名前); let café_2 = größe;");
+
+		assert_eq!(tokens[1].0, Token::Identifier("名前".to_string()));
+		assert_eq!(tokens[6].0, Token::FunctionName("println".to_string()));
+		assert_eq!(tokens[8].0, Token::Identifier("名前".to_string()));
+		assert_eq!(tokens[12].0, Token::Identifier("café_2".to_string()));
+		assert_eq!(tokens[14].0, Token::Identifier("größe".to_string()));
+	}
+
+	#[test]
+	fn lines_and_columns() {
+		let tokens = lex("let a: str = \"🦀\n\";\n  a = \"一\nニ\";\nprintln(a);");
+
+		let lines = tokens
+			.iter()
+			.map(|(_, span)| (span.line, span.column))
+			.collect::<Vec<_>>();
+
+		assert_eq!(lines[4], (1, 14));
+		assert_eq!(lines[6], (3, 3));
+		assert_eq!(lines[10], (5, 1));
+		assert_eq!(lines[11], (5, 8));
+	}
+
+	#[test]
+	fn comments() {
+		let file = "let a: int = 4 / 2; // halve\n// done\n";
+		let mut lexer = Lexer::new(file);
+		let tokens = lexer.run().unwrap().to_vec();
+
+		assert_eq!(tokens.len(), 8);
+		assert_eq!(tokens[5].0, Token::Division);
+
+		let comments = lexer.comments();
+		assert_eq!(comments[0].0, "// halve");
+		assert_eq!(comments[1].0, "// done");
+		assert_eq!((comments[1].1.line, comments[1].1.column), (2, 1));
+	}
+
+	#[test]
+	fn paths() {
+		let tokens = lex("let n: int = string::len(s);");
+
+		assert_eq!(tokens[4].0, Token::FunctionName("string::len".to_string()));
+		assert_eq!(tokens[4].1.range, 13..24);
+	}
+
+	#[test]
+	fn ranges() {
+		let tokens = lex("a[1..n]");
+
+		assert_eq!(tokens[2].0, Token::Int(1));
+		assert_eq!(tokens[3].0, Token::Range);
+		assert_eq!(tokens[4].0, Token::Identifier("n".to_string()));
+	}
+
+	#[test]
+	fn floats() {
+		let tokens = lex("1.5 2..3");
+
+		assert_eq!(tokens[0].0, Token::Float(1.5));
+		assert_eq!(tokens[1].0, Token::Int(2));
+		assert_eq!(tokens[2].0, Token::Range);
+	}
+
+	#[test]
+	fn escapes() {
+		let tokens = lex(r#""tab\t \"quoted\" \u{1F980}""#);
+
+		assert_eq!(tokens[0].0, Token::Str("tab\t \"quoted\" 🦀".to_string()));
 	}
 }
@@ -0,0 +1,493 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::{
+	ast::{Ast, AstNode, Expression},
+	error::Warning,
+	token::{Token, Tokens},
+};
+
+#[derive(Debug, Clone)]
+struct Declaration {
+	name: String,
+	range: Range<usize>,
+	used: bool,
+}
+
+/// Looks for likely mistakes in an [`Ast`] without running it.
+#[derive(Debug, Clone, Default)]
+pub struct Linter {
+	warnings: Vec<Warning>,
+	/// Variables declared at the top level and in the function being linted,
+	/// blocks share the scope they are in.
+	scopes: Vec<Vec<Declaration>>,
+	functions: Vec<Declaration>,
+	calls: HashSet<String>,
+	/// Names read inside functions which were not declared before them.
+	globals: HashSet<String>,
+	tokens: Tokens,
+	/// The statement being linted.
+	statement: Range<usize>,
+	/// Starts of the comparisons already warned about.
+	comparisons: HashSet<usize>,
+}
+
+impl Linter {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the warnings for `ast` parsed from `tokens`, ordered by where
+	/// they are in the file.
+	pub fn run(&mut self, ast: &Ast, tokens: Tokens) -> Vec<Warning> {
+		self.tokens = tokens;
+		self.scopes.push(vec![]);
+		self.block(ast);
+
+		let globals = std::mem::take(&mut self.globals);
+		for declaration in self.scopes.last_mut().unwrap() {
+			declaration.used |= globals.contains(&declaration.name);
+		}
+		self.pop();
+
+		for function in std::mem::take(&mut self.functions) {
+			if !self.calls.contains(&function.name) && !function.name.starts_with('_') {
+				self.warnings.push(Warning::new(
+					"0002",
+					&format!("function `{}` is never called", function.name),
+					"unused function",
+					function.range,
+				));
+			}
+		}
+
+		let mut warnings = std::mem::take(&mut self.warnings);
+		warnings.sort_by_key(|warning| warning.range.start);
+		warnings
+	}
+
+	fn pop(&mut self) {
+		for declaration in self.scopes.pop().unwrap_or_default() {
+			if !declaration.used && !declaration.name.starts_with('_') {
+				self.warnings.push(Warning::new(
+					"0001",
+					&format!("variable `{}` is never read", declaration.name),
+					&format!(
+						"unused variable, prefix it with `_` if this is intentional: `_{}`",
+						declaration.name
+					),
+					declaration.range,
+				));
+			}
+		}
+	}
+
+	/// Range of `name` where it is declared in `range`.
+	fn name(&self, name: &str, range: &Range<usize>) -> Range<usize> {
+		self
+			.tokens
+			.iter()
+			.find(|(token, span)| {
+				range.contains(&span.range.start)
+					&& matches!(token, Token::Identifier(n) | Token::FunctionName(n) if n == name)
+			})
+			.map_or(range.clone(), |(_, span)| span.range.clone())
+	}
+
+	fn declare(&mut self, name: &str, range: &Range<usize>) {
+		let range = &self.name(name, range);
+		let shadowed = self
+			.scopes
+			.iter()
+			.flatten()
+			.any(|declaration| declaration.name == name);
+
+		if shadowed {
+			self.warnings.push(Warning::new(
+				"0006",
+				&format!("`{name}` shadows an earlier declaration"),
+				&format!("`{name}` was already declared"),
+				range.clone(),
+			));
+		}
+
+		if let Some(scope) = self.scopes.last_mut() {
+			scope.push(Declaration {
+				name: name.to_string(),
+				range: range.clone(),
+				used: false,
+			});
+		}
+	}
+
+	fn read(&mut self, name: &str) {
+		let declaration = self
+			.scopes
+			.iter_mut()
+			.rev()
+			.flat_map(|scope| scope.iter_mut().rev())
+			.find(|declaration| declaration.name == name);
+
+		match declaration {
+			Some(declaration) => declaration.used = true,
+			None => {
+				self.globals.insert(name.to_string());
+			}
+		}
+	}
+
+	fn block(&mut self, ast: &Ast) {
+		let mut diverged = false;
+		let mut unreachable: Option<Range<usize>> = None;
+
+		for (node, range) in ast {
+			if diverged {
+				let start = unreachable.as_ref().map_or(range.start, |r| r.start);
+				unreachable = Some(start..range.end);
+			}
+
+			self.statement = range.clone();
+			self.node(node, range);
+			diverged |= diverges(node);
+		}
+
+		if let Some(range) = unreachable {
+			self.warnings.push(Warning::new(
+				"0003",
+				"unreachable code",
+				"this code will never run",
+				range,
+			));
+		}
+	}
+
+	fn node(&mut self, node: &AstNode, range: &Range<usize>) {
+		match node {
+			AstNode::Assignment(ident, _, expression) => {
+				self.expression(expression);
+				self.declare(ident, range);
+			}
+			AstNode::ReAssignment(_, expression) => self.expression(expression),
+			AstNode::VecReAssignment(_, index, expression) => {
+				self.expression(index);
+				self.expression(expression);
+			}
+			AstNode::If(condition, then) => {
+				self.expression(condition);
+				self.block(then);
+			}
+			AstNode::IfElse(condition, then, otherwise) => {
+				self.expression(condition);
+				self.block(then);
+				self.block(otherwise);
+			}
+			AstNode::Loop(body) => {
+				if !escapes(body) {
+					self.warnings.push(Warning::new(
+						"0004",
+						"loop never ends",
+						"this loop has no `break`, `return` or `exit`",
+						range.clone(),
+					));
+				}
+				self.block(body);
+			}
+			AstNode::FunctionCall(name, params) => self.call(name, params),
+			AstNode::FunctionDeclaration(name, params, datatype, body) => {
+				let declaration = self.name(name, range);
+				if self.functions.iter().any(|function| function.name == *name) {
+					self.warnings.push(Warning::new(
+						"0006",
+						&format!("`{name}` shadows an earlier declaration"),
+						&format!("`{name}` was already declared"),
+						declaration.clone(),
+					));
+				}
+				self.functions.push(Declaration {
+					name: name.clone(),
+					range: declaration.clone(),
+					used: false,
+				});
+
+				self.scopes.push(vec![]);
+				for param in params {
+					self.declare(&param.name, range);
+				}
+				self.block(body);
+				self.pop();
+
+				if let Some(datatype) = datatype {
+					if !returns(body) {
+						self.warnings.push(Warning::new(
+							"0007",
+							&format!("function `{name}` may not return a value"),
+							&format!("not every path through this function returns a `{datatype}`"),
+							declaration,
+						));
+					}
+				}
+			}
+			AstNode::Test(_, body) => {
+				self.scopes.push(vec![]);
+				self.block(body);
+				self.pop();
+			}
+			AstNode::Return(expression) | AstNode::Exit(expression) => self.expression(expression),
+			AstNode::Break | AstNode::Error => {}
+		}
+	}
+
+	/// Range of the first comparison of `op` and `bool` in the statement which
+	/// was not warned about yet.
+	fn comparison(&mut self, op: &Token, bool: bool) -> Range<usize> {
+		let statement = self
+			.tokens
+			.iter()
+			.filter(|(_, span)| self.statement.contains(&span.range.start))
+			.collect::<Vec<_>>();
+
+		for pair in statement.windows(2) {
+			let [(first, a), (second, b)] = pair else {
+				continue;
+			};
+
+			let matches = (first == op && *second == Token::Bool(bool))
+				|| (*first == Token::Bool(bool) && second == op);
+
+			if matches && self.comparisons.insert(a.range.start) {
+				return a.range.start..b.range.end;
+			}
+		}
+
+		self.statement.clone()
+	}
+
+	fn call(&mut self, name: &str, params: &[Expression]) {
+		self.calls.insert(name.to_string());
+
+		for param in params {
+			self.expression(param);
+		}
+	}
+
+	fn expression(&mut self, expression: &Expression) {
+		match expression {
+			Expression::BinaryOperation(lhs, op, rhs) => {
+				let redundant = match (op, lhs.as_ref(), rhs.as_ref()) {
+					(Token::IsEqual, Expression::Bool(true), _)
+					| (Token::IsEqual, _, Expression::Bool(true)) => Some(true),
+					(Token::IsNotEqual, Expression::Bool(false), _)
+					| (Token::IsNotEqual, _, Expression::Bool(false)) => Some(false),
+					_ => None,
+				};
+
+				if let Some(bool) = redundant {
+					let range = self.comparison(op, bool);
+					self.warnings.push(Warning::new(
+						"0005",
+						&format!(
+							"comparing a bool with `{} {bool}` is redundant",
+							op.as_string()
+						),
+						"use the bool directly",
+						range,
+					));
+				}
+
+				self.expression(lhs);
+				self.expression(rhs);
+			}
+			Expression::UnaryOperation(_, expression) => self.expression(expression),
+			Expression::Identifier(ident) => self.read(ident),
+			Expression::VecIndex(ident, index) => {
+				self.read(ident);
+				self.expression(index);
+			}
+			Expression::VecSlice(ident, start, end) => {
+				self.read(ident);
+				self.expression(start);
+				self.expression(end);
+			}
+			Expression::FunctionCall(name, params) => self.call(name, params),
+			Expression::Vector(items, _) => {
+				for item in items {
+					self.expression(item);
+				}
+			}
+			Expression::Block(ast, expression) => {
+				self.block(ast);
+				self.expression(expression);
+			}
+			Expression::Str(_) | Expression::Int(_) | Expression::Float(_) | Expression::Bool(_) => {}
+		}
+	}
+}
+
+/// Whether control never continues past `node`.
+fn diverges(node: &AstNode) -> bool {
+	match node {
+		AstNode::Return(_) | AstNode::Break | AstNode::Exit(_) => true,
+		AstNode::IfElse(_, then, otherwise) => {
+			then.iter().any(|(node, _)| diverges(node))
+				&& otherwise.iter().any(|(node, _)| diverges(node))
+		}
+		_ => false,
+	}
+}
+
+/// Whether every path through `ast` ends in a `return` or `exit`.
+fn returns(ast: &Ast) -> bool {
+	ast.iter().any(|(node, _)| match node {
+		AstNode::Return(_) | AstNode::Exit(_) => true,
+		AstNode::IfElse(_, then, otherwise) => returns(then) && returns(otherwise),
+		AstNode::Loop(body) => !breaks(body),
+		_ => false,
+	})
+}
+
+/// Whether `ast` contains a `break` for the loop it is in.
+fn breaks(ast: &Ast) -> bool {
+	ast.iter().any(|(node, _)| match node {
+		AstNode::Break => true,
+		AstNode::If(_, then) => breaks(then),
+		AstNode::IfElse(_, then, otherwise) => breaks(then) || breaks(otherwise),
+		_ => false,
+	})
+}
+
+/// Whether anything in `ast` leaves the loop it is in.
+fn escapes(ast: &Ast) -> bool {
+	ast.iter().any(|(node, _)| match node {
+		AstNode::Break | AstNode::Return(_) | AstNode::Exit(_) => true,
+		AstNode::If(_, then) => escapes(then),
+		AstNode::IfElse(_, then, otherwise) => escapes(then) || escapes(otherwise),
+		AstNode::Loop(body) => escapes_loop(body),
+		_ => false,
+	})
+}
+
+/// Whether anything in a nested loop leaves the outer loop too.
+fn escapes_loop(ast: &Ast) -> bool {
+	ast.iter().any(|(node, _)| match node {
+		AstNode::Return(_) | AstNode::Exit(_) => true,
+		AstNode::If(_, then) | AstNode::Loop(then) => escapes_loop(then),
+		AstNode::IfElse(_, then, otherwise) => escapes_loop(then) || escapes_loop(otherwise),
+		_ => false,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Linter;
+	use crate::{lexer::Lexer, parser::Parser};
+
+	/// Codes of the warnings for `file`, in order.
+	fn codes(file: &str) -> Vec<String> {
+		let tokens = Lexer::new(file).run().unwrap().to_vec();
+		let (ast, errors) = Parser::new().run(tokens.clone());
+		assert!(errors.is_empty(), "{errors:?}");
+
+		Linter::new()
+			.run(&ast, tokens)
+			.into_iter()
+			.map(|warning| warning.code)
+			.collect()
+	}
+
+	#[test]
+	fn unused_variable() {
+		assert_eq!(codes("let a: int = 1;"), ["0001"]);
+		assert_eq!(codes("let a: int = 1; println(a);"), [] as [&str; 0]);
+		assert_eq!(codes("fn f(a: int) {} f(1);"), ["0001"]);
+	}
+
+	#[test]
+	fn unused_function() {
+		assert_eq!(codes("fn f() {}"), ["0002"]);
+		assert_eq!(codes("fn f() {} f();"), [] as [&str; 0]);
+	}
+
+	#[test]
+	fn unreachable_code() {
+		assert_eq!(
+			codes("fn f() -> int { return 1; println(1); } f();"),
+			["0003"]
+		);
+		assert_eq!(
+			codes("fn f(a: bool) -> int { if a { return 1; } else { return 2; } println(1); } f(true);"),
+			["0003"]
+		);
+	}
+
+	#[test]
+	fn endless_loop() {
+		assert_eq!(codes("loop { println(1); }"), ["0004"]);
+		assert_eq!(codes("loop { break; }"), [] as [&str; 0]);
+		assert_eq!(codes("loop { if true { exit(0); } }"), [] as [&str; 0]);
+	}
+
+	#[test]
+	fn redundant_comparison() {
+		assert_eq!(codes("let a: bool = true; println(a == true);"), ["0005"]);
+		assert_eq!(codes("let a: bool = true; println(false != a);"), ["0005"]);
+		assert_eq!(
+			codes("let a: bool = true; println(a == false);"),
+			[] as [&str; 0]
+		);
+	}
+
+	#[test]
+	fn shadowing() {
+		assert_eq!(
+			codes("let a: int = 1; let a: int = a; println(a);"),
+			["0006"]
+		);
+		assert_eq!(codes("fn f() {} fn f() {} f();"), ["0006"]);
+	}
+
+	#[test]
+	fn missing_return() {
+		assert_eq!(
+			codes("fn f(a: bool) -> int { if a { return 1; } } f(true);"),
+			["0007"]
+		);
+		assert_eq!(
+			codes("fn f(a: bool) -> int { if a { return 1; } else { return 2; } } f(true);"),
+			[] as [&str; 0]
+		);
+	}
+
+	#[test]
+	fn underscore_opts_out() {
+		assert_eq!(codes("let _a: int = 1;"), [] as [&str; 0]);
+		assert_eq!(codes("fn _f(_a: int) {}"), [] as [&str; 0]);
+	}
+
+	#[test]
+	fn endless_loops_return() {
+		// only leaving through `return` or `exit`, so the function never
+		// finishes without a value
+		assert_eq!(codes("fn f() -> int { loop {} } f();"), ["0004"]);
+		assert_eq!(
+			codes("fn f(a: bool) -> int { loop { if a { return 1; } } } f(true);"),
+			[] as [&str; 0]
+		);
+		assert_eq!(
+			codes("fn f(a: bool) -> int { loop { if a { break; } } } f(true);"),
+			["0007"]
+		);
+	}
+
+	#[test]
+	fn nested_loops() {
+		// a `break` only leaves the loop it is in
+		assert_eq!(codes("loop { loop { break; } }"), ["0004"]);
+		assert_eq!(
+			codes("fn f() -> int { loop { loop { return 1; } } } f();"),
+			[] as [&str; 0]
+		);
+		assert_eq!(
+			codes("fn f() -> int { loop { loop { break; } } } f();"),
+			["0004"]
+		);
+	}
+}
@@ -0,0 +1,648 @@
+use std::{collections::HashMap, error::Error as StdError, ops::Range};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response, ResponseError};
+use lsp_types::{
+	notification::{
+		DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+		Notification as LspNotification, PublishDiagnostics,
+	},
+	request::{
+		Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as LspRequest,
+	},
+	CompletionItem, CompletionItemKind, CompletionOptions, Diagnostic, DiagnosticSeverity,
+	DocumentSymbol, Hover, HoverContents, HoverProviderCapability, Location, MarkupContent,
+	MarkupKind, NumberOrString, OneOf, Position, PublishDiagnosticsParams, ServerCapabilities,
+	SymbolKind, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+use crate::{
+	ast::{Ast, AstNode},
+	data::{Function, Param},
+	lexer::Lexer,
+	linter::Linter,
+	parser::Parser,
+	standardlibrary::StandardLibrary,
+	token::{Token, Tokens},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn StdError + Send + Sync>>;
+
+/// Runs the language server over stdio until the client shuts it down.
+pub fn run() -> Result<()> {
+	let (connection, io_threads) = Connection::stdio();
+
+	let capabilities = ServerCapabilities {
+		text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+		hover_provider: Some(HoverProviderCapability::Simple(true)),
+		definition_provider: Some(OneOf::Left(true)),
+		document_symbol_provider: Some(OneOf::Left(true)),
+		completion_provider: Some(CompletionOptions::default()),
+		..Default::default()
+	};
+	connection.initialize(serde_json::to_value(capabilities)?)?;
+
+	let mut documents: HashMap<Url, Document> = HashMap::new();
+
+	for message in &connection.receiver {
+		match message {
+			Message::Request(request) => {
+				if connection.handle_shutdown(&request)? {
+					break;
+				}
+				let response = respond(&documents, request);
+				connection.sender.send(Message::Response(response))?;
+			}
+			Message::Notification(notification) => {
+				let method = notification.method.clone();
+				let uri = match update(&mut documents, notification) {
+					Ok(Some(uri)) => uri,
+					Ok(None) => continue,
+					// a notification has no response to report it in
+					Err(e) => {
+						eprintln!("invalid params for `{method}`: {e}");
+						continue;
+					}
+				};
+
+				let params =
+					PublishDiagnosticsParams::new(uri.clone(), documents[&uri].diagnostics.clone(), None);
+				let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+				connection
+					.sender
+					.send(Message::Notification(notification))?;
+			}
+			Message::Response(_) => {}
+		}
+	}
+
+	drop(connection);
+	io_threads.join()?;
+	Ok(())
+}
+
+/// Applies a change to the open documents, returning the document whose
+/// diagnostics should be published.
+fn update(
+	documents: &mut HashMap<Url, Document>,
+	notification: Notification,
+) -> serde_json::Result<Option<Url>> {
+	Ok(Some(match notification.method.as_str() {
+		DidOpenTextDocument::METHOD => {
+			let params: <DidOpenTextDocument as LspNotification>::Params =
+				serde_json::from_value(notification.params)?;
+			let document = Document::new(params.text_document.text);
+			documents.insert(params.text_document.uri.clone(), document);
+			params.text_document.uri
+		}
+		DidChangeTextDocument::METHOD => {
+			let params: <DidChangeTextDocument as LspNotification>::Params =
+				serde_json::from_value(notification.params)?;
+			let text = match params.content_changes.into_iter().last() {
+				Some(change) => change.text,
+				None => return Ok(None),
+			};
+			documents.insert(params.text_document.uri.clone(), Document::new(text));
+			params.text_document.uri
+		}
+		DidCloseTextDocument::METHOD => {
+			let params: <DidCloseTextDocument as LspNotification>::Params =
+				serde_json::from_value(notification.params)?;
+			documents.remove(&params.text_document.uri);
+			return Ok(None);
+		}
+		_ => return Ok(None),
+	}))
+}
+
+fn respond(documents: &HashMap<Url, Document>, request: Request) -> Response {
+	let id = request.id.clone();
+
+	match result(documents, request) {
+		Ok(result) => Response::new_ok(id, result),
+		Err(error) => Response {
+			id,
+			result: None,
+			error: Some(error),
+		},
+	}
+}
+
+fn result(
+	documents: &HashMap<Url, Document>,
+	request: Request,
+) -> std::result::Result<Option<serde_json::Value>, ResponseError> {
+	let result = match request.method.as_str() {
+		HoverRequest::METHOD => {
+			let params = params::<HoverRequest>(request.params)?;
+			let position = params.text_document_position_params;
+
+			documents
+				.get(&position.text_document.uri)
+				.and_then(|document| document.hover(position.position))
+				.map(serde_json::to_value)
+		}
+		GotoDefinition::METHOD => {
+			let params = params::<GotoDefinition>(request.params)?;
+			let position = params.text_document_position_params;
+			let uri = position.text_document.uri;
+
+			documents
+				.get(&uri)
+				.and_then(|document| document.definition(uri.clone(), position.position))
+				.map(serde_json::to_value)
+		}
+		DocumentSymbolRequest::METHOD => {
+			let params = params::<DocumentSymbolRequest>(request.params)?;
+
+			documents
+				.get(&params.text_document.uri)
+				.map(|document| serde_json::to_value(document.document_symbols(&document.ast)))
+		}
+		Completion::METHOD => {
+			let params = params::<Completion>(request.params)?;
+
+			documents
+				.get(&params.text_document_position.text_document.uri)
+				.map(|document| serde_json::to_value(document.completions()))
+		}
+		method => {
+			return Err(error(
+				ErrorCode::MethodNotFound,
+				format!("unknown method `{method}`"),
+			))
+		}
+	};
+
+	result
+		.transpose()
+		.map_err(|e| error(ErrorCode::InternalError, e.to_string()))
+}
+
+/// The params of a request of type `R`, which are invalid if they do not
+/// match it.
+fn params<R: LspRequest>(
+	params: serde_json::Value,
+) -> std::result::Result<R::Params, ResponseError> {
+	serde_json::from_value(params).map_err(|e| {
+		error(
+			ErrorCode::InvalidParams,
+			format!("invalid params for `{}`: {e}", R::METHOD),
+		)
+	})
+}
+
+fn error(code: ErrorCode, message: String) -> ResponseError {
+	ResponseError {
+		code: code as i32,
+		message,
+		data: None,
+	}
+}
+
+#[derive(Debug, Clone)]
+enum SymbolType {
+	Variable(String),
+	Function(Function),
+	Test,
+}
+
+/// A variable or function declared in a document.
+#[derive(Debug, Clone)]
+struct Symbol {
+	name: String,
+	datatype: SymbolType,
+	/// The whole declaration.
+	range: Range<usize>,
+	/// The name in the declaration.
+	selection: Range<usize>,
+	/// Where the declaration can be referred to.
+	scope: Range<usize>,
+}
+
+impl Symbol {
+	fn detail(&self) -> String {
+		match &self.datatype {
+			SymbolType::Variable(datatype) => format!("let {}: {datatype}", self.name),
+			SymbolType::Function(function) => function.to_string(),
+			SymbolType::Test => format!("test fn {}()", self.name),
+		}
+	}
+}
+
+/// An open document with everything the lexer and parser found in it.
+#[derive(Debug, Clone)]
+struct Document {
+	text: String,
+	tokens: Tokens,
+	ast: Ast,
+	symbols: Vec<Symbol>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+	fn new(text: String) -> Self {
+		let mut document = Self {
+			text,
+			tokens: vec![],
+			ast: vec![],
+			symbols: vec![],
+			diagnostics: vec![],
+		};
+
+		let errors = match Lexer::new(&document.text).run() {
+			Ok(tokens) => {
+				document.tokens = tokens.clone();
+				let (ast, errors) = Parser::new().run(tokens.clone());
+				document.ast = ast;
+				errors
+			}
+			Err(error) => vec![error],
+		};
+
+		let warnings = if errors.is_empty() {
+			Linter::new().run(&document.ast, document.tokens.clone())
+		} else {
+			vec![]
+		};
+
+		document.diagnostics = errors
+			.into_iter()
+			.map(|error| Diagnostic {
+				range: document.range(&error.range),
+				severity: Some(DiagnosticSeverity::ERROR),
+				code: Some(NumberOrString::String("E".to_owned() + &error.code)),
+				source: Some("oxido".to_string()),
+				message: format!("{}\n{}", error.message, error.note),
+				..Default::default()
+			})
+			.chain(warnings.into_iter().map(|warning| Diagnostic {
+				range: document.range(&warning.range),
+				severity: Some(DiagnosticSeverity::WARNING),
+				code: Some(NumberOrString::String("W".to_owned() + &warning.code)),
+				source: Some("oxido".to_string()),
+				message: format!("{}\n{}", warning.message, warning.note),
+				..Default::default()
+			}))
+			.collect();
+
+		let ast = document.ast.clone();
+		document.collect(&ast, 0..document.text.len());
+
+		document
+	}
+
+	/// Records the declarations in `ast`, which are visible in `scope`.
+	fn collect(&mut self, ast: &Ast, scope: Range<usize>) {
+		for (node, range) in ast {
+			match node {
+				AstNode::Assignment(name, datatype, _) => {
+					self.declare(
+						name,
+						SymbolType::Variable(datatype.to_string()),
+						range,
+						&scope,
+					);
+				}
+				AstNode::FunctionDeclaration(name, params, datatype, body) => {
+					let function = Function::new(name.clone(), params.clone(), datatype.clone(), vec![]);
+					self.declare(name, SymbolType::Function(function), range, &scope);
+
+					for Param { name, datatype } in params {
+						self.declare(
+							name,
+							SymbolType::Variable(datatype.to_string()),
+							range,
+							range,
+						);
+					}
+					self.collect(body, range.clone());
+				}
+				AstNode::Test(name, body) => {
+					self.declare(name, SymbolType::Test, range, &scope);
+					self.collect(body, range.clone());
+				}
+				AstNode::If(_, body) | AstNode::Loop(body) => self.collect(body, range.clone()),
+				AstNode::IfElse(_, body, otherwise) => {
+					self.collect(body, range.clone());
+					self.collect(otherwise, range.clone());
+				}
+				_ => {}
+			}
+		}
+	}
+
+	fn declare(
+		&mut self,
+		name: &str,
+		datatype: SymbolType,
+		range: &Range<usize>,
+		scope: &Range<usize>,
+	) {
+		let selection = self
+			.tokens
+			.iter()
+			.find(|(token, span)| {
+				range.contains(&span.range.start)
+					&& matches!(token, Token::Identifier(n) | Token::FunctionName(n) if n == name)
+			})
+			.map_or(range.clone(), |(_, span)| span.range.clone());
+
+		self.symbols.push(Symbol {
+			name: name.to_string(),
+			datatype,
+			range: range.clone(),
+			selection,
+			scope: scope.clone(),
+		});
+	}
+
+	/// The name of the identifier or function at `position`.
+	fn word(&self, position: Position) -> Option<(&str, usize)> {
+		let offset = self.offset(position);
+
+		self.tokens.iter().find_map(|(token, span)| match token {
+			Token::Identifier(name) | Token::FunctionName(name)
+				if span.range.start <= offset && offset <= span.range.end =>
+			{
+				Some((name.as_str(), offset))
+			}
+			_ => None,
+		})
+	}
+
+	/// The declaration `name` refers to at `offset`, preferring the innermost
+	/// and most recent one.
+	fn lookup(&self, name: &str, offset: usize) -> Option<&Symbol> {
+		let mut symbols = self.symbols.iter().filter(|symbol| symbol.name == name);
+
+		symbols
+			.clone()
+			.filter(|symbol| symbol.scope.contains(&offset) || symbol.scope.end == offset)
+			.filter(|symbol| {
+				matches!(symbol.datatype, SymbolType::Function(_)) || symbol.range.start <= offset
+			})
+			.max_by_key(|symbol| (symbol.scope.start, symbol.range.start))
+			.or_else(|| symbols.next())
+	}
+
+	fn hover(&self, position: Position) -> Option<Hover> {
+		let (name, offset) = self.word(position)?;
+
+		let value = match self.lookup(name, offset) {
+			Some(symbol) => format!("```oxido\n{}\n```", symbol.detail()),
+			None if StandardLibrary::default().contains(name) => {
+				format!("```oxido\nfn {name}\n```\nstandard library function")
+			}
+			None => return None,
+		};
+
+		Some(Hover {
+			contents: HoverContents::Markup(MarkupContent {
+				kind: MarkupKind::Markdown,
+				value,
+			}),
+			range: None,
+		})
+	}
+
+	fn definition(&self, uri: Url, position: Position) -> Option<Location> {
+		let (name, offset) = self.word(position)?;
+		let symbol = self.lookup(name, offset)?;
+
+		Some(Location::new(uri, self.range(&symbol.selection)))
+	}
+
+	#[allow(deprecated)]
+	fn document_symbols(&self, ast: &Ast) -> Vec<DocumentSymbol> {
+		let mut symbols = vec![];
+
+		for (node, range) in ast {
+			let (name, kind, children) = match node {
+				AstNode::Assignment(name, _, _) => (name, SymbolKind::VARIABLE, vec![]),
+				AstNode::FunctionDeclaration(name, _, _, body) | AstNode::Test(name, body) => {
+					(name, SymbolKind::FUNCTION, self.document_symbols(body))
+				}
+				AstNode::If(_, body) | AstNode::Loop(body) => {
+					symbols.extend(self.document_symbols(body));
+					continue;
+				}
+				AstNode::IfElse(_, body, otherwise) => {
+					symbols.extend(self.document_symbols(body));
+					symbols.extend(self.document_symbols(otherwise));
+					continue;
+				}
+				_ => continue,
+			};
+
+			let symbol = match self
+				.symbols
+				.iter()
+				.find(|symbol| symbol.name == *name && symbol.range == *range)
+			{
+				Some(symbol) => symbol,
+				None => continue,
+			};
+
+			symbols.push(DocumentSymbol {
+				name: name.clone(),
+				detail: Some(symbol.detail()),
+				kind,
+				tags: None,
+				deprecated: None,
+				range: self.range(&symbol.range),
+				selection_range: self.range(&symbol.selection),
+				children: Some(children),
+			});
+		}
+
+		symbols
+	}
+
+	fn completions(&self) -> Vec<CompletionItem> {
+		let std = StandardLibrary::default();
+
+		let mut items = std
+			.names()
+			.iter()
+			.map(|name| CompletionItem {
+				label: name.to_string(),
+				kind: Some(CompletionItemKind::FUNCTION),
+				detail: Some("standard library function".to_string()),
+				..Default::default()
+			})
+			.chain(std.constants().iter().map(|name| CompletionItem {
+				label: name.to_string(),
+				kind: Some(CompletionItemKind::CONSTANT),
+				detail: Some("standard library constant".to_string()),
+				..Default::default()
+			}))
+			.collect::<Vec<_>>();
+
+		for symbol in &self.symbols {
+			if items.iter().any(|item| item.label == symbol.name) {
+				continue;
+			}
+
+			let kind = match symbol.datatype {
+				SymbolType::Variable(_) => CompletionItemKind::VARIABLE,
+				SymbolType::Function(_) => CompletionItemKind::FUNCTION,
+				SymbolType::Test => continue,
+			};
+			items.push(CompletionItem {
+				label: symbol.name.clone(),
+				kind: Some(kind),
+				detail: Some(symbol.detail()),
+				..Default::default()
+			});
+		}
+
+		items
+	}
+
+	/// Converts a byte offset to an LSP position, which counts UTF-16 code
+	/// units.
+	fn position(&self, offset: usize) -> Position {
+		let before = &self.text[..offset.min(self.text.len())];
+		let line = before.matches('\n').count();
+		let start = before.rfind('\n').map_or(0, |i| i + 1);
+		let character = before[start..].encode_utf16().count();
+
+		Position::new(line as u32, character as u32)
+	}
+
+	fn offset(&self, position: Position) -> usize {
+		let start = self
+			.text
+			.split_inclusive('\n')
+			.take(position.line as usize)
+			.map(str::len)
+			.sum::<usize>();
+
+		let mut units = 0;
+		for (i, ch) in self.text[start..].char_indices() {
+			if units >= position.character as usize || ch == '\n' {
+				return start + i;
+			}
+			units += ch.len_utf16();
+		}
+		self.text.len()
+	}
+
+	fn range(&self, range: &Range<usize>) -> lsp_types::Range {
+		lsp_types::Range::new(self.position(range.start), self.position(range.end))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{respond, update, Document};
+	use lsp_server::{ErrorCode, Notification, Request, RequestId};
+	use lsp_types::{HoverContents, Position, Url};
+	use serde_json::json;
+	use std::collections::HashMap;
+
+	const FILE: &str =
+		"let a: int = 1;\nfn f(a: str) -> str {\n\treturn a;\n}\nprintln(f(\"x\"), a);\n";
+
+	fn detail(document: &Document, line: u32, character: u32) -> Option<String> {
+		match document.hover(Position::new(line, character))?.contents {
+			HoverContents::Markup(markup) => Some(markup.value),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn utf16_positions() {
+		let document = Document::new(String::from("let s: str = \"é𝄞\";\nlet t: int = 1;"));
+
+		// `é` is one UTF-16 code unit and two bytes, `𝄞` two units and four bytes
+		assert_eq!(document.position(14), Position::new(0, 14));
+		assert_eq!(document.position(16), Position::new(0, 15));
+		assert_eq!(document.position(20), Position::new(0, 17));
+		assert_eq!(document.position(23), Position::new(1, 0));
+
+		for offset in [14, 16, 20, 23, 27] {
+			assert_eq!(document.offset(document.position(offset)), offset);
+		}
+		// past the end of a line or of the file
+		assert_eq!(document.offset(Position::new(0, 100)), 22);
+		assert_eq!(document.offset(Position::new(5, 0)), document.text.len());
+	}
+
+	#[test]
+	fn hover() {
+		let document = Document::new(String::from(FILE));
+
+		assert_eq!(
+			detail(&document, 4, 16).unwrap(),
+			"```oxido\nlet a: int\n```"
+		);
+		// the parameter shadows the global inside the function
+		assert_eq!(
+			detail(&document, 2, 8).unwrap(),
+			"```oxido\nlet a: str\n```"
+		);
+		assert!(detail(&document, 4, 9)
+			.unwrap()
+			.contains("fn f(a: str) -> str"));
+		assert!(detail(&document, 4, 2)
+			.unwrap()
+			.contains("standard library function"));
+		assert_eq!(detail(&document, 4, 13), None);
+	}
+
+	#[test]
+	fn definition() {
+		let document = Document::new(String::from(FILE));
+		let uri = Url::parse("file:///main.oxi").unwrap();
+
+		let location = document
+			.definition(uri.clone(), Position::new(4, 9))
+			.unwrap();
+		assert_eq!(location.uri, uri);
+		assert_eq!(location.range.start, Position::new(1, 3));
+
+		let location = document
+			.definition(uri.clone(), Position::new(2, 8))
+			.unwrap();
+		assert_eq!(location.range.start, Position::new(1, 5));
+
+		let location = document.definition(uri, Position::new(4, 16)).unwrap();
+		assert_eq!(location.range.start, Position::new(0, 4));
+	}
+
+	#[test]
+	fn invalid_params() {
+		let uri = Url::parse("file:///main.oxi").unwrap();
+		let mut documents = HashMap::new();
+		documents.insert(uri.clone(), Document::new(String::from(FILE)));
+
+		let request = Request::new(
+			RequestId::from(1),
+			String::from("textDocument/hover"),
+			json!({}),
+		);
+		let response = respond(&documents, request);
+		assert_eq!(
+			response.error.unwrap().code,
+			ErrorCode::InvalidParams as i32
+		);
+
+		let params = json!({
+			"textDocument": { "uri": uri },
+			"position": { "line": 4, "character": 16 },
+		});
+		let request = Request::new(
+			RequestId::from(2),
+			String::from("textDocument/hover"),
+			params,
+		);
+		let response = respond(&documents, request);
+		assert!(response.error.is_none());
+		assert!(response.result.is_some());
+
+		let notification = Notification::new(String::from("textDocument/didOpen"), json!(1));
+		assert!(update(&mut documents, notification).is_err());
+		assert_eq!(documents.len(), 1);
+	}
+}
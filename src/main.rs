@@ -1,36 +1,50 @@
-use clap::Parser as ClapParser;
-use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
-use std::{fs::{metadata, read_to_string},process::exit,time::Instant};
+use clap::{Parser as ClapParser, Subcommand};
+use sandbox::{Capabilities, Limits};
+use std::{
+	cell::RefCell,
+	fs::{metadata, read_to_string, write},
+	path::PathBuf,
+	process::exit,
+	rc::Rc,
+	time::{Duration, Instant},
+};
 
 mod ast;
 mod data;
+mod debugger;
 mod error;
+mod formatter;
+mod helper;
 mod interpreter;
 mod lexer;
+mod linter;
+mod lsp;
 mod parser;
+mod profiler;
+mod repl;
+mod sandbox;
 mod standardlibrary;
+mod tester;
 mod token;
 
 pub struct Config {
 	debug: bool,
 	dry_run: bool,
 	time: bool,
-}
-
-impl Config {
-	pub fn new(debug: bool, dry_run: bool, time: bool) -> Self {
-		Self {
-			debug,
-			dry_run,
-			time,
-		}
-	}
+	profile: bool,
+	coverage: Option<String>,
+	capabilities: Capabilities,
+	limits: Limits,
+	args: Vec<String>,
 }
 
 #[derive(ClapParser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
 struct Args {
+	#[clap(subcommand)]
+	command: Option<Command>,
+
 	/// Whether to output debug information
 	#[clap(short, long, value_parser)]
 	debug: bool,
@@ -43,6 +57,42 @@ struct Args {
 	#[clap(short, long, value_parser)]
 	time: bool,
 
+	/// Whether to print call counts and times of functions and hits of lines
+	#[clap(long, value_parser)]
+	profile: bool,
+
+	/// The path of file to write the line and function coverage to, in lcov format
+	#[clap(long, value_parser)]
+	coverage: Option<String>,
+
+	/// Whether to stop the program from starting processes
+	#[clap(long, value_parser)]
+	no_process: bool,
+
+	/// Whether to only allow the program to write to stdout and stderr
+	#[clap(long, value_parser)]
+	sandbox: bool,
+
+	/// A directory the `fs` module may access, denying every other one
+	#[clap(long, value_parser)]
+	allow_path: Vec<PathBuf>,
+
+	/// The number of statements and loop iterations the program may run
+	#[clap(long, value_parser)]
+	max_instructions: Option<u64>,
+
+	/// The number of bytes the values of variables may take up
+	#[clap(long, value_parser)]
+	max_memory: Option<usize>,
+
+	/// The number of milliseconds the program may run for
+	#[clap(long, value_parser)]
+	timeout: Option<u64>,
+
+	/// The number of function calls that may be nested, 500 unless given
+	#[clap(long, value_parser)]
+	max_depth: Option<usize>,
+
 	/// The code which is to be executed
 	#[clap(short, long, value_parser)]
 	code: Option<String>,
@@ -50,13 +100,72 @@ struct Args {
 	/// The path of file which is to be executed
 	#[clap()]
 	input: Option<String>,
+
+	/// The arguments passed to the program, after `--`
+	#[clap(last = true)]
+	args: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Runs the language server over stdio
+	Lsp,
+	/// Checks Oxido files for likely mistakes
+	Lint {
+		/// The files which are to be linted
+		#[clap(required = true)]
+		files: Vec<String>,
+	},
+	/// Runs the tests declared with `test fn` in Oxido files
+	Test {
+		/// The files whose tests are to be run
+		#[clap(required = true)]
+		files: Vec<String>,
+	},
+	/// Runs an Oxido file in the step debugger
+	Debug {
+		/// The path of file which is to be debugged
+		file: String,
+	},
+	/// Formats Oxido files in place
+	Fmt {
+		/// The files which are to be formatted
+		#[clap(required = true)]
+		files: Vec<String>,
+
+		/// Whether to only check if the files are formatted
+		#[clap(long, value_parser)]
+		check: bool,
+	},
 }
 
 fn main() {
 	let args = Args::parse();
 
-	let contents = if args.code.is_some() {
-		args.code.unwrap()
+	match args.command {
+		Some(Command::Lsp) => {
+			if let Err(e) = lsp::run() {
+				eprintln!("error in language server, {e}");
+				exit(1);
+			}
+			return;
+		}
+		Some(Command::Lint { files }) => lint(files),
+		Some(Command::Test { files }) => test(files),
+		Some(Command::Debug { file }) => {
+			let contents = match read_to_string(&file) {
+				Ok(text) => text,
+				Err(error) => panic!("error while reading file, {error}"),
+			};
+			debugger::run(&file, contents);
+			return;
+		}
+		Some(Command::Fmt { files, check }) => fmt(files, check),
+		None => {}
+	}
+
+	let contents = if let Some(code) = args.code {
+		code
 	} else if args.input.is_some() {
 		let mut input = args.input.clone().unwrap();
 
@@ -87,55 +196,121 @@ fn main() {
 			Err(error) => panic!("error while reading file, {error}"),
 		}
 	} else {
-		println!(
-			"Welcome to Oxido v{}\nTo exit, press CTRL+C or CTRL+D",
-			version()
-		);
-		let mut rl = DefaultEditor::new().unwrap();
-		let mut interpreter = interpreter::Interpreter::new("REPL", String::new());
-		loop {
-			let readline = rl.readline("\x1b[1m\x1b[32m[In]:\x1b[0m ");
-			match readline {
-				Ok(line) => {
-					if line.trim() == "exit" {
-						break;
-					}
-					interpreter.set_file(line.clone());
-
-					print!("\x1b[1m\x1b[31m[Out]:\x1b[0m ");
-
-					interpreter.run(
-						parser::Parser::new("REPL", "")
-							.run(lexer::Lexer::new("REPL", &line).run().unwrap().to_vec())
-							.unwrap()
-							.to_vec(),
-					);
-
-					interpreter.set_file(String::new());
-					println!("\n");
-				}
-				Err(ReadlineError::Interrupted) => {
-					println!("CTRL-C");
-					break;
-				}
-				Err(ReadlineError::Eof) => {
-					println!("CTRL-D");
-					break;
-				}
-				Err(err) => {
-					println!("Error: {:?}", err);
-					break;
-				}
-			}
-		}
+		repl::run();
 		exit(1);
 	};
 
-	let config = Config::new(args.debug, args.dry_run, args.time);
+	let mut capabilities = if args.sandbox {
+		Capabilities::sandboxed()
+	} else {
+		Capabilities::default()
+	};
+	if args.sandbox || !args.allow_path.is_empty() {
+		capabilities.paths = Some(args.allow_path);
+	}
+	if args.no_process {
+		capabilities.process = false;
+	}
+
+	let config = Config {
+		debug: args.debug,
+		dry_run: args.dry_run,
+		time: args.time,
+		profile: args.profile,
+		coverage: args.coverage,
+		capabilities,
+		limits: Limits {
+			instructions: args.max_instructions,
+			memory: args.max_memory,
+			time: args.timeout.map(Duration::from_millis),
+			depth: args.max_depth.or(Limits::default().depth),
+		},
+		args: args.args,
+	};
 
 	run(args.input.unwrap_or_default(), contents, config);
 }
 
+fn lint(files: Vec<String>) {
+	let mut failed = false;
+
+	for file in files {
+		let contents = match read_to_string(&file) {
+			Ok(text) => text,
+			Err(error) => panic!("error while reading file, {error}"),
+		};
+
+		let tokens = match lexer::Lexer::new(&contents).run() {
+			Ok(tokens) => tokens.to_vec(),
+			Err(e) => {
+				error::report(&file, &contents, &[e]);
+				failed = true;
+				continue;
+			}
+		};
+
+		let (ast, errors) = parser::Parser::new().run(tokens.clone());
+		if !errors.is_empty() {
+			error::report(&file, &contents, &errors);
+			failed = true;
+			continue;
+		}
+
+		let warnings = linter::Linter::new().run(&ast, tokens);
+		error::warn(&file, &contents, &warnings);
+	}
+
+	exit(failed as i32);
+}
+
+fn test(files: Vec<String>) {
+	let mut failed = false;
+
+	for file in files {
+		let contents = match read_to_string(&file) {
+			Ok(text) => text,
+			Err(error) => panic!("error while reading file, {error}"),
+		};
+
+		failed |= !tester::run(&file, &contents);
+	}
+
+	exit(failed as i32);
+}
+
+fn fmt(files: Vec<String>, check: bool) {
+	let mut failed = false;
+
+	for file in files {
+		let contents = match read_to_string(&file) {
+			Ok(text) => text,
+			Err(error) => panic!("error while reading file, {error}"),
+		};
+
+		let formatted = match formatter::format(&contents) {
+			Ok(formatted) => formatted,
+			Err(errors) => {
+				error::report(&file, &contents, &errors);
+				failed = true;
+				continue;
+			}
+		};
+
+		if formatted == contents {
+			continue;
+		}
+
+		if check {
+			println!("`{file}` is not formatted");
+			failed = true;
+		} else if let Err(error) = write(&file, formatted) {
+			panic!("error while writing file, {error}");
+		}
+	}
+
+	exit(failed as i32);
+}
+
 pub fn version() -> String {
 	env!("CARGO_PKG_VERSION").to_string()
 }
@@ -143,16 +318,27 @@ pub fn version() -> String {
 pub fn run(name: String, contents: String, config: Config) {
 	let main = Instant::now();
 
-	let mut lexer = lexer::Lexer::new(&name, &contents);
-	let tokens = lexer.run().unwrap();
+	let mut lexer = lexer::Lexer::new(&contents);
+	let tokens = match lexer.run() {
+		Ok(tokens) => tokens,
+		Err(e) => {
+			error::report(&name, &contents, &[e]);
+			exit(1);
+		}
+	};
 
 	if config.debug {
 		let duration = main.elapsed();
 		println!("LEXER: {tokens:?}\n\nTIME: {duration:?}\n");
 	}
 
-	let parser = parser::Parser::new(&name, &contents);
-	let ast = parser.run(tokens.to_vec()).unwrap();
+	let mut parser = parser::Parser::new();
+	let (ast, errors) = parser.run(tokens.to_vec());
+
+	if !errors.is_empty() {
+		error::report(&name, &contents, &errors);
+		exit(1);
+	}
 
 	if config.debug {
 		let duration = main.elapsed();
@@ -162,13 +348,48 @@ pub fn run(name: String, contents: String, config: Config) {
 		return;
 	}
 
-	let mut interpreter = interpreter::Interpreter::new(&name, contents);
-	interpreter.run(ast.to_vec());
+	let mut interpreter = interpreter::Interpreter::sandboxed(config.capabilities, config.limits);
+	interpreter.set_args(config.args);
+
+	let profiler = if config.profile || config.coverage.is_some() {
+		let profiler = Rc::new(RefCell::new(profiler::Profiler::new(&contents, &ast)));
+		interpreter.set_hook(profiler.clone());
+		Some(profiler)
+	} else {
+		None
+	};
+
+	let result = interpreter.run(ast);
+
+	if let Some(profiler) = profiler {
+		let profiler = profiler.borrow();
+
+		if config.profile {
+			eprint!("\n{}", profiler.report());
+		}
+		if let Some(coverage) = &config.coverage {
+			if let Err(error) = write(coverage, profiler.lcov(&name)) {
+				panic!("error while writing file, {error}");
+			}
+		}
+	}
+
+	if let Err(e) = result {
+		error::report(&name, &contents, &[e]);
+		exit(1);
+	}
+	if interpreter.exited() {
+		exit(interpreter.exit_code());
+	}
 
 	if config.debug || config.time {
 		let duration = main.elapsed();
 		println!("\nTIME: {duration:?}");
 	}
 
-	println!()
+	println!();
+
+	if interpreter.exit_code() != 0 {
+		exit(interpreter.exit_code());
+	}
 }
@@ -1,842 +1,649 @@
-use std::{iter::Peekable, ops::Range, vec::IntoIter};
+use std::ops::Range;
 
 use crate::{
 	ast::{Ast, AstNode, Expression},
-	data::Param,
-	error::error,
-	token::{Token, Tokens},
+	data::{DataType, Param},
+	error::Error,
+	token::{Span, Token, Tokens},
 };
 
-#[derive(Debug, Clone)]
-pub struct Parser<'a> {
-	name: &'a str,
-	file: &'a str,
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+	tokens: Tokens,
+	pos: usize,
+	errors: Vec<Error>,
 }
 
-impl<'a> Parser<'a> {
-	pub fn new(name: &'a str, file: &'a str) -> Self {
-		Self { name, file }
+impl Parser {
+	pub fn new() -> Self {
+		Self::default()
 	}
 
-	pub fn run(&'a self, tokens: Tokens) -> Option<Ast> {
-		let ast = self.match_tokens(tokens)?;
+	/// Parses `tokens` into an [`Ast`], statements which could not be parsed
+	/// are replaced by [`AstNode::Error`] and the errors are returned with it.
+	pub fn run(&mut self, tokens: Tokens) -> (Ast, Vec<Error>) {
+		self.tokens = tokens;
+		self.pos = 0;
 
-		Some(ast)
-	}
-
-	fn match_tokens(&'a self, tokens: Tokens) -> Option<Ast> {
-		let mut pos = 0;
-		let mut nodes: Ast = vec![];
-
-		loop {
-			let mut statements = vec![];
-			let token = tokens.get(pos);
+		let mut ast = vec![];
 
-			if token.is_none() {
-				break;
+		while let Some((token, span)) = self.peek() {
+			if *token == Token::RCurly {
+				self.errors.push(Error::new(
+					"0001",
+					&format!("token `{}` was not expected here", token.as_string()),
+					&format!("token `{}` was not expected here", token.as_string()),
+					span.range.clone(),
+				));
+				self.next();
+				continue;
 			}
 
-			let token = token?;
-
-			if token.0 == Token::Let {
-				loop {
-					let token = tokens.get(pos);
-
-					if token.is_none() {
-						break;
-					}
-
-					let token = token?;
-
-					if token.0 == Token::Semicolon {
-						statements.push(token);
-						break;
-					}
-
-					statements.push(token);
-
-					pos += 1;
-				}
-
-				nodes.push(self.parse(statements)?);
-			} else if let Token::Identifier(_) = token.0 {
-				loop {
-					let token = tokens.get(pos);
-
-					if token.is_none() {
-						break;
-					}
-
-					let token = token?;
-
-					if token.0 == Token::Semicolon {
-						statements.push(token);
-						break;
-					}
-
-					statements.push(token);
-
-					pos += 1;
-				}
-
-				nodes.push(self.parse(statements)?);
-			} else if let Token::FunctionName(_) = token.0 {
-				loop {
-					let token = tokens.get(pos);
-
-					if token.is_none() {
-						break;
-					}
+			if let Some(node) = self.statement() {
+				ast.push(node);
+			}
+		}
 
-					let token = token?;
+		(ast, std::mem::take(&mut self.errors))
+	}
 
-					if token.0 == Token::Semicolon {
-						statements.push(token);
-						break;
-					}
+	/// Parses `tokens` as a single expression spanning all of them.
+	pub fn run_expression(&mut self, tokens: Tokens) -> Result<(Expression, Range<usize>), Error> {
+		self.tokens = tokens;
+		self.pos = 0;
 
-					statements.push(token);
+		let start = self.start();
+		let expression = self.expression(0)?;
+		let end = self.end();
 
-					pos += 1;
-				}
+		if self.peek().is_some() {
+			return Err(self.unexpected("the end of the expression"));
+		}
 
-				nodes.push(self.parse(statements)?);
-			} else if token.0 == Token::If {
-				let mut depth = 0;
-				loop {
-					let token = tokens.get(pos);
+		Ok((expression, start..end))
+	}
 
-					if token.is_none() {
-						break;
-					}
+	fn peek(&self) -> Option<&(Token, Span)> {
+		self.tokens.get(self.pos)
+	}
 
-					let token = token?;
+	fn peek_is(&self, token: &Token) -> bool {
+		matches!(self.peek(), Some((t, _)) if t == token)
+	}
 
-					if token.0 == Token::LCurly {
-						depth += 1;
-					}
+	fn peek_nth_is(&self, n: usize, token: &Token) -> bool {
+		matches!(self.tokens.get(self.pos + n), Some((t, _)) if t == token)
+	}
 
-					if token.0 == Token::RCurly {
-						depth -= 1;
-						if depth == 0 {
-							statements.push(token);
-							if tokens.get(pos + 1).is_some() && tokens.get(pos + 1)?.0 == Token::Else {
-								pos += 1;
-								statements.push(tokens.get(pos + 1)?);
-								continue;
-							}
-							break;
-						}
-					}
+	fn next(&mut self) -> Option<(Token, Span)> {
+		let token = self.tokens.get(self.pos).cloned();
+		if token.is_some() {
+			self.pos += 1;
+		}
+		token
+	}
 
-					statements.push(token);
+	/// Start of the next token, or the end of the last one once the stream is
+	/// exhausted.
+	fn start(&self) -> usize {
+		match self.peek() {
+			Some((_, span)) => span.range.start,
+			None => self.end(),
+		}
+	}
 
-					pos += 1;
-				}
-				nodes.push(self.parse(statements)?);
-			} else if token.0 == Token::Loop || token.0 == Token::Fn {
-				let mut depth = 0;
-				loop {
-					let token = tokens.get(pos);
-
-					if token.is_none() {
-						break;
-					}
+	/// End of the most recently consumed token.
+	fn end(&self) -> usize {
+		match self.pos.checked_sub(1).and_then(|pos| self.tokens.get(pos)) {
+			Some((_, span)) => span.range.end,
+			None => 0,
+		}
+	}
 
-					let token = token?;
+	fn unexpected(&self, expected: &str) -> Error {
+		match self.peek() {
+			Some((token, span)) => Error::new(
+				"0001",
+				&format!("expected {expected} found `{}`", token.as_string()),
+				&format!("use {expected} here"),
+				span.range.clone(),
+			),
+			None => Error::new(
+				"0001",
+				&format!("expected {expected} found end of file"),
+				&format!("use {expected} here"),
+				self.end()..self.end(),
+			),
+		}
+	}
 
-					if token.0 == Token::RCurly {
-						depth -= 1;
-						if depth == 0 {
-							statements.push(token);
-							break;
-						}
-					}
-					if token.0 == Token::LCurly {
-						depth += 1;
-					}
+	fn expect(&mut self, token: Token) -> Result<(), Error> {
+		if self.peek_is(&token) {
+			self.next();
+			Ok(())
+		} else {
+			Err(self.unexpected(&format!("`{}`", token.as_string())))
+		}
+	}
 
-					statements.push(token);
+	/// Parses a statement, on failure the error is recorded and the tokens up
+	/// to the next `;` or closing `}` are skipped.
+	fn statement(&mut self) -> Option<(AstNode, Range<usize>)> {
+		let start = self.start();
+
+		match self.parse_statement() {
+			Ok(node) => node,
+			Err(error) => {
+				self.errors.push(error);
+				self.recover();
+				Some((AstNode::Error, start..self.end().max(start)))
+			}
+		}
+	}
 
-					pos += 1;
-				}
+	fn recover(&mut self) {
+		let mut depth = 0;
 
-				nodes.push(self.parse(statements)?);
-			} else {
-				match &token.0 {
-					Token::Break => nodes.push((AstNode::Break, token.1..token.0.len())),
-					Token::Return => loop {
-						let token = tokens.get(pos);
-
-						if token.is_none() {
-							break;
-						}
-
-						let token = token?;
-
-						if token.0 == Token::Semicolon {
-							statements.push(token);
-							nodes.push(self.parse(statements)?);
-							break;
-						}
-
-						statements.push(token);
-
-						pos += 1;
-					},
-					Token::Exit => loop {
-						let token = tokens.get(pos);
-
-						if token.is_none() {
-							break;
-						}
-
-						let token = token?;
-
-						if token.0 == Token::Semicolon {
-							statements.push(token);
-							nodes.push(self.parse(statements)?);
-							break;
-						}
-
-						statements.push(token);
-
-						pos += 1;
-					},
-					Token::Semicolon => {}
-					t => {
-						error(
-							self.name,
-							self.file,
-							"0001",
-							&format!("token `{}` was not expected here", t.as_string()),
-							&format!("token `{}` was not expected here", t.as_string()),
-							&(token.1..token.1 + token.0.len()),
-						);
-					}
+		while let Some((token, _)) = self.peek() {
+			match token {
+				Token::Semicolon if depth == 0 => {
+					self.next();
+					break;
 				}
+				Token::RCurly if depth == 0 => break,
+				Token::RCurly => depth -= 1,
+				Token::LCurly => depth += 1,
+				_ => {}
 			}
-
-			pos += 1;
+			self.next();
 		}
-
-		Some(nodes)
 	}
 
-	fn parse(&'a self, tokens: Vec<&'a (Token, usize)>) -> Option<(AstNode, Range<usize>)> {
-		let mut stream = tokens.iter().peekable();
-
-		let token = *stream.next()?;
+	fn parse_statement(&mut self) -> Result<Option<(AstNode, Range<usize>)>, Error> {
+		let start = self.start();
 
-		let node: (AstNode, Range<usize>) = if token.0 == Token::Let {
-			let t = &stream.next()?;
-			if let Token::Identifier(ident) = &t.0 {
-				let t = stream.peek()?;
-				let datatype = if let Token::DataType(datatype) = &t.0 {
-					stream.next()?;
-					datatype.clone()
-				} else {
-					error(
-						self.name,
-						self.file,
-						"E0010",
-						"expected data type",
-						"expected data type",
-						&(t.1 - 1..t.1 + t.0.len() - 1),
-					)
+		let node = match self.peek().map(|(token, _)| token) {
+			Some(Token::Semicolon) => {
+				self.next();
+				return Ok(None);
+			}
+			Some(Token::Let) => {
+				self.next();
+				let ident = self.identifier()?;
+
+				let datatype = match self.peek() {
+					Some((Token::DataType(datatype), _)) => datatype.clone(),
+					Some((_, span)) => {
+						return Err(Error::new(
+							"0010",
+							"expected data type",
+							"expected data type",
+							span.range.clone(),
+						))
+					}
+					None => return Err(self.unexpected("a data type")),
 				};
+				self.next();
 
-				self.check(stream.next().copied(), Token::Equal);
-
-				let mut tokens = stream.collect::<Vec<_>>();
-
-				let t = tokens.pop();
+				self.expect(Token::Equal)?;
+				let expression = self.expression(0)?;
+				self.expect(Token::Semicolon)?;
 
-				self.check(t.copied(), Token::Semicolon);
-
-				let tokens = tokens.iter().map(|f| **f).collect::<Vec<_>>();
-
-				let (expression, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
-
-				(
-					AstNode::Assignment(ident.to_string(), datatype, expression),
-					token.1..t?.1,
-				)
-			} else {
-				error(
-					self.name,
-					self.file,
-					"0001",
-					&format!("expected identifier found {}", t.0.as_string()),
-					"use an identifier here",
-					&(t.1 - 1..t.1 + t.0.len() - 1),
-				);
+				AstNode::Assignment(ident, datatype, expression)
 			}
-		} else if let Token::Identifier(ident) = &token.0 {
-			if stream.peek()?.0 == Token::LSquare {
-				stream.next()?;
-				let mut tokens = stream.collect::<Vec<_>>();
-
-				let t = tokens.pop();
+			Some(Token::If) => self.parse_if()?,
+			Some(Token::Loop) => {
+				self.next();
+				AstNode::Loop(self.block()?)
+			}
+			Some(Token::Identifier(ident)) if ident == "test" && self.peek_nth_is(1, &Token::Fn) => {
+				self.next();
+				self.next();
+				let name = self.function_name()?;
+
+				self.expect(Token::LParen)?;
+				if !self.peek_is(&Token::RParen) {
+					return Err(self.unexpected("`)`, tests do not take parameters"));
+				}
+				self.expect(Token::RParen)?;
 
-				self.check(t.copied(), Token::Semicolon);
+				AstNode::Test(name, self.block()?)
+			}
+			Some(Token::Fn) => {
+				self.next();
+				let name = self.function_name()?;
 
-				let mut index_tokens = vec![];
-				let mut expr_tokens = vec![];
+				self.expect(Token::LParen)?;
 
-				let mut flag = false;
-				for token in tokens {
-					if token.0 == Token::RSquare {
-						flag = true;
-						continue;
-					}
+				let mut params = vec![];
+				while !self.peek_is(&Token::RParen) {
+					let ident = self.identifier()?;
+					let datatype = self.datatype()?;
+					params.push(Param::new(ident, datatype));
 
-					if !flag {
-						index_tokens.push(*token);
-					} else {
-						expr_tokens.push(*token);
+					if !self.peek_is(&Token::RParen) {
+						self.expect(Token::Comma)?;
 					}
 				}
+				self.expect(Token::RParen)?;
 
-				let (index, _) = self.pratt_parser(index_tokens.into_iter().peekable(), 0);
-
-				self.check(Some(expr_tokens.remove(0)), Token::Equal);
-
-				let (expression, _) = self.pratt_parser(expr_tokens.into_iter().peekable(), 0);
-
-				(
-					AstNode::VecReAssignment(ident.to_string(), index, expression),
-					token.1..t?.1,
-				)
-			} else {
-				self.check(stream.next().copied(), Token::Equal);
-
-				let mut tokens = stream.collect::<Vec<_>>();
-
-				let t = tokens.pop();
-
-				self.check(t.copied(), Token::Semicolon);
-
-				let tokens = tokens.iter().map(|f| **f).collect::<Vec<_>>();
-
-				let (expression, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
+				let datatype = match self.peek() {
+					Some((Token::DataType(_), _)) => Some(self.datatype()?),
+					_ => None,
+				};
 
-				(
-					AstNode::ReAssignment(ident.to_string(), expression),
-					token.1..t?.1,
-				)
+				AstNode::FunctionDeclaration(name, params, datatype, self.block()?)
 			}
-		} else if token.0 == Token::If {
-			let mut tokens = vec![];
-			let mut then = vec![];
-			let mut otherwise = None;
-			let mut flag = false;
-
-			for token in stream {
-				if token.0 == Token::LCurly {
-					flag = true;
-					continue;
-				} else if token.0 == Token::Else {
-					otherwise = Some(vec![]);
-				}
-				if flag {
-					if otherwise.is_some() {
-						otherwise.as_mut()?.push((*token).to_owned());
-					} else {
-						then.push((*token).to_owned());
-					}
-				} else {
-					tokens.push(token);
+			Some(Token::Break) => {
+				self.next();
+				if self.peek_is(&Token::Semicolon) {
+					self.next();
 				}
+				AstNode::Break
 			}
-
-			let mut t = then.pop();
-
-			self.check(t.as_ref(), Token::RCurly);
-
-			let tokens = tokens.iter().map(|f| **f).collect::<Vec<_>>();
-
-			let (expression, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
-
-			if otherwise.is_some() {
-				t = otherwise.as_mut()?.pop();
-				self.check(Some(&otherwise.as_mut()?.remove(0)), Token::Else);
-				self.check(t.as_ref(), Token::RCurly);
-				(
-					AstNode::IfElse(
-						expression,
-						self.match_tokens(then)?,
-						self.match_tokens(otherwise?)?,
-					),
-					token.1..t?.1,
-				)
-			} else {
-				(
-					AstNode::If(expression, self.match_tokens(then)?),
-					token.1..t?.1,
-				)
+			Some(Token::Return) => {
+				self.next();
+				let expression = self.expression(0)?;
+				self.expect(Token::Semicolon)?;
+				AstNode::Return(expression)
 			}
-		} else if token.0 == Token::Loop {
-			let mut statements = vec![];
-
-			self.check(stream.next().copied(), Token::LCurly);
-
-			for token in stream {
-				statements.push((*token).to_owned());
+			Some(Token::Exit) => {
+				self.next();
+				let expression = self.expression(0)?;
+				self.expect(Token::Semicolon)?;
+				AstNode::Exit(expression)
 			}
+			Some(_) => {
+				let expression = self.expression(0)?;
+				self.expression_statement(start, expression)?
+			}
+			None => return Err(self.unexpected("a statement")),
+		};
 
-			let t = statements.pop();
+		Ok(Some((node, start..self.end())))
+	}
 
-			self.check(t.as_ref(), Token::RCurly);
+	/// Parses the rest of a statement starting with `expression`, which is
+	/// either the target of an assignment or a call.
+	fn expression_statement(
+		&mut self,
+		start: usize,
+		expression: Expression,
+	) -> Result<AstNode, Error> {
+		if self.peek_is(&Token::Equal) {
+			self.next();
+			let value = self.expression(0)?;
+			self.expect(Token::Semicolon)?;
+
+			return match expression {
+				Expression::Identifier(ident) => Ok(AstNode::ReAssignment(ident, value)),
+				Expression::VecIndex(ident, index) => Ok(AstNode::VecReAssignment(ident, *index, value)),
+				_ => Err(Error::new(
+					"0001",
+					"invalid left hand side of assignment",
+					"cannot assign to this expression",
+					start..self.end(),
+				)),
+			};
+		}
+		self.expect(Token::Semicolon)?;
 
-			(AstNode::Loop(self.match_tokens(statements)?), token.1..t?.1)
-		} else if let Token::FunctionName(ident) = &token.0 {
-			self.check(stream.next().copied(), Token::LParen);
+		match expression {
+			Expression::FunctionCall(name, params) => Ok(AstNode::FunctionCall(name, params)),
+			_ => Err(Error::new(
+				"0001",
+				"expected a statement found an expression",
+				"the value of this expression is unused",
+				start..self.end(),
+			)),
+		}
+	}
 
-			let tokens = stream.map(|f| f.to_owned()).collect::<Vec<_>>();
-			let mut params = vec![];
-			let mut expression = vec![];
+	/// Parses `if <expression> { ... }` with any number of `else if` branches
+	/// and an optional `else` branch.
+	fn parse_if(&mut self) -> Result<AstNode, Error> {
+		self.expect(Token::If)?;
 
-			let mut end = 0;
-			let mut depth = 1;
+		let condition = self.expression(0)?;
+		let then = self.block()?;
 
-			for token in tokens {
-				if token.0 == Token::LParen {
-					depth += 1;
-				}
+		if !self.peek_is(&Token::Else) {
+			return Ok(AstNode::If(condition, then));
+		}
+		self.next();
 
-				if token.0 == Token::RParen {
-					depth -= 1;
+		let otherwise = if self.peek_is(&Token::If) {
+			let start = self.start();
+			let node = self.parse_if()?;
+			vec![(node, start..self.end())]
+		} else {
+			self.block()?
+		};
 
-					if depth == 0 {
-						if !expression.is_empty() {
-							let (data, _) = self.pratt_parser(expression.clone().into_iter().peekable(), 0);
+		Ok(AstNode::IfElse(condition, then, otherwise))
+	}
 
-							params.push(data);
+	fn block(&mut self) -> Result<Ast, Error> {
+		self.expect(Token::LCurly)?;
 
-							expression.clear();
-							continue;
-						}
-						end = token.1;
-						break;
+		let mut ast = vec![];
+		loop {
+			match self.peek() {
+				Some((Token::RCurly, _)) => break,
+				Some(_) => {
+					if let Some(node) = self.statement() {
+						ast.push(node);
 					}
 				}
-
-				if token.0 == Token::Comma {
-					let (data, _) = self.pratt_parser(expression.clone().into_iter().peekable(), 0);
-
-					params.push(data);
-
-					expression.clear();
-
-					continue;
-				}
-
-				expression.push(token);
+				None => return Err(self.unexpected("`}`")),
 			}
+		}
+		self.expect(Token::RCurly)?;
 
-			(
-				AstNode::FunctionCall(ident.to_string(), params),
-				token.1..end,
-			)
-		} else if token.0 == Token::Fn {
-			let t = &stream.next()?;
-			if let Token::FunctionName(name) = &t.0 {
-				let mut params = vec![];
-
-				loop {
-					let token = stream.peek();
-
-					if token.is_none() {
-						break;
-					}
-
-					let token = &stream.next()?.0;
+		Ok(ast)
+	}
 
-					if token == &Token::RParen {
-						break;
-					}
+	/// Parses `{ statements... expression }`, the value of the block is the
+	/// value of its trailing expression. Statements starting with an
+	/// expression are only told apart from it by what follows, so it is
+	/// parsed once and finished as a statement if it is not followed by `}`.
+	fn block_expression(&mut self) -> Result<Expression, Error> {
+		self.expect(Token::LCurly)?;
 
-					if token == &Token::Comma {
-						continue;
-					}
+		let mut ast = vec![];
+		loop {
+			let statement = match self.peek() {
+				Some((Token::Identifier(ident), _)) => ident == "test" && self.peek_nth_is(1, &Token::Fn),
+				Some((token, _)) => matches!(
+					token,
+					Token::Let
+						| Token::If
+						| Token::Loop
+						| Token::Fn
+						| Token::Break
+						| Token::Return
+						| Token::Exit
+						| Token::Semicolon
+				),
+				None => false,
+			};
 
-					if let Token::Identifier(name) = token {
-						if let Token::DataType(datatype) = &stream.next()?.0 {
-							params.push(Param::new(name.to_string(), datatype.clone()));
-						}
-					}
+			if statement {
+				if let Some(node) = self.statement() {
+					ast.push(node);
 				}
+				continue;
+			}
 
-				let t = stream.next()?;
-				let datatype = if let Token::DataType(datatype) = &t.0 {
-					datatype
-				} else {
-					error(
-						self.name,
-						self.file,
-						"E0010",
-						"expected data type",
-						"expected data type",
-						&(t.1 - 1..t.1 + t.0.len() - 1),
-					)
-				};
-
-				self.check(stream.next().copied(), Token::LCurly);
-
-				let mut statements = vec![];
-
-				loop {
-					let token = stream.peek();
-
-					if token.is_none() {
-						break;
-					}
+			let start = self.start();
+			let expression = self.expression(0)?;
 
-					let token = stream.next()?;
+			if self.peek_is(&Token::RCurly) {
+				self.next();
+				return Ok(Expression::Block(ast, Box::new(expression)));
+			}
 
-					statements.push((*token).to_owned());
+			match self.expression_statement(start, expression) {
+				Ok(node) => ast.push((node, start..self.end())),
+				Err(error) => {
+					self.errors.push(error);
+					self.recover();
+					ast.push((AstNode::Error, start..self.end().max(start)));
 				}
+			}
+		}
+	}
 
-				let t = statements.pop();
-
-				self.check(t.as_ref(), Token::RCurly);
-
-				(
-					AstNode::FunctionDeclaration(
-						name.to_string(),
-						params,
-						Some(datatype.clone()),
-						self.match_tokens(statements)?,
-					),
-					token.1..t?.1,
-				)
-			} else {
-				error(
-					self.name,
-					self.file,
+	fn function_name(&mut self) -> Result<String, Error> {
+		let name = match self.peek() {
+			Some((Token::FunctionName(name), _)) => name.to_string(),
+			Some((token, span)) => {
+				return Err(Error::new(
 					"0001",
-					&format!("expected name of function found {}", t.0.as_string()),
+					&format!("expected name of function found {}", token.as_string()),
 					"use function name here",
-					&(t.1 - 1..t.1 + t.0.len() - 1),
-				);
+					span.range.clone(),
+				))
 			}
-		} else if token.0 == Token::Return {
-			let mut tokens = stream.collect::<Vec<_>>();
-
-			let t = tokens.pop();
-
-			self.check(t.copied(), Token::Semicolon);
-
-			let tokens = tokens.iter().map(|f| **f).collect::<Vec<_>>();
-
-			let (expression, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
-
-			(AstNode::Return(expression), token.1..t?.1)
-		} else if token.0 == Token::Exit {
-			let mut tokens = stream.collect::<Vec<_>>();
-
-			let t = tokens.pop();
-
-			self.check(t.copied(), Token::Semicolon);
-
-			let tokens = tokens.iter().map(|f| **f).collect::<Vec<_>>();
-
-			let (expression, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
-
-			(AstNode::Exit(expression), token.1..t?.1)
-		} else {
-			error(
-				self.name,
-				self.file,
-				"0001",
-				&format!("{} was not expected", token.0.as_string()),
-				"did not expect this",
-				&(token.1 - 1..token.1 + token.0.len()),
-			);
+			None => return Err(self.unexpected("name of function")),
 		};
+		self.next();
 
-		Some(node)
+		Ok(name)
 	}
 
-	fn check(&self, t1: Option<&(Token, usize)>, t2: Token) -> bool {
-		if t1.is_none() || t1.unwrap().0 != t2 {
-			error(
-				self.name,
-				self.file,
-				"0001",
-				&format!(
-					"expected `{}` found {}",
-					t2.as_string(),
-					t1.unwrap().0.as_string()
-				),
-				&format!("use `{}` here", t2.as_string()),
-				&(t1.unwrap().1 - 1..t1.unwrap().1 + t2.len() - 1),
-			);
-		};
-
-		true
+	fn identifier(&mut self) -> Result<String, Error> {
+		match self.peek() {
+			Some((Token::Identifier(ident), _)) => {
+				let ident = ident.to_string();
+				self.next();
+				Ok(ident)
+			}
+			_ => Err(self.unexpected("an identifier")),
+		}
 	}
 
-	fn pratt_parser(
-		&'a self,
-		mut lexer: Peekable<IntoIter<&'a (Token, usize)>>,
-		prec: u16,
-	) -> (Expression, Peekable<IntoIter<&'a (Token, usize)>>) {
-		let token = &lexer.next().unwrap();
-		let mut expr: Option<Expression> = None;
-
-		match &token.0 {
-			Token::Identifier(i) => {
-				let t = lexer.peek();
-
-				if t.is_some() && t.unwrap().0 == Token::LSquare {
-					lexer.next().unwrap();
-					let mut tokens = vec![];
-					let mut depth = 1;
-
-					loop {
-						let t = lexer.next();
-
-						if t.is_none() {
-							break;
-						}
-						let t = t.unwrap();
-
-						if t.0 == Token::LSquare {
-							depth += 1;
-						} else if t.0 == Token::RSquare {
-							depth -= 1;
-						}
-
-						tokens.push(t);
-
-						if depth == 0 {
-							break;
-						}
-					}
-
-					let t = tokens.pop();
-
-					self.check(t, Token::RSquare);
-
-					let (index, _) = self.pratt_parser(tokens.into_iter().peekable(), 0);
-
-					expr = Some(Expression::VecIndex(i.to_string(), Box::new(index)));
-				} else {
-					expr = Some(Expression::Identifier(i.to_string()));
-				}
-			}
-			Token::Bool(bool) => {
-				expr = Some(Expression::Bool(*bool));
-			}
-			Token::Str(str) => {
-				expr = Some(Expression::Str(str.to_string()));
-			}
-			Token::LParen => {
-				let exp;
-				(exp, lexer) = self.pratt_parser(lexer, 0);
-				expr = Some(exp);
+	fn datatype(&mut self) -> Result<DataType, Error> {
+		match self.peek() {
+			Some((Token::DataType(datatype), _)) => {
+				let datatype = datatype.clone();
+				self.next();
+				Ok(datatype)
 			}
-			Token::LSquare => {
-				let mut tokens = vec![];
-				let mut depth = 1;
-
-				loop {
-					let t = lexer.next();
-
-					if t.is_none() {
-						break;
-					}
-					let t = t.unwrap();
-
-					if t.0 == Token::LSquare {
-						depth += 1;
-					} else if t.0 == Token::RSquare {
-						depth -= 1;
-					}
-
-					tokens.push(t);
+			_ => Err(self.unexpected("a data type")),
+		}
+	}
 
-					if depth == 0 {
-						break;
-					}
-				}
+	/// Parses comma separated expressions up to and including `end`.
+	fn list(&mut self, end: Token) -> Result<Vec<Expression>, Error> {
+		let mut expressions = vec![];
 
-				let mut params = vec![];
-				let mut expression: Tokens = vec![];
+		while !self.peek_is(&end) {
+			expressions.push(self.expression(0)?);
 
-				for token in tokens {
-					if token.0 == Token::RSquare {
-						if !expression.is_empty() {
-							let lex = expression.iter().collect::<Vec<_>>().into_iter().peekable();
-							let (data, _) = self.pratt_parser(lex, 0);
+			if !self.peek_is(&end) {
+				self.expect(Token::Comma)?;
+			}
+		}
+		self.expect(end)?;
 
-							params.push(data);
-						}
-						break;
-					}
+		Ok(expressions)
+	}
 
-					if token.0 == Token::Comma {
-						let lex = expression.iter().collect::<Vec<_>>().into_iter().peekable();
-						let (data, _) = self.pratt_parser(lex, 0);
+	fn expression(&mut self, prec: u16) -> Result<Expression, Error> {
+		let mut expr = self.primary()?;
 
-						params.push(data);
+		while let Some((op, _)) = self.peek() {
+			let op = op.clone();
 
-						expression.clear();
-						continue;
-					}
+			let power = match self.infix_binding_power(&op) {
+				Some(power) => power,
+				None => break,
+			};
 
-					expression.push(token.to_owned());
-				}
-				let datatype = if params.first().is_some() {
-					params.first().unwrap().infer_datatype()
-				} else {
-					None
-				};
-				expr = Some(Expression::Vector(params, datatype))
-			}
-			Token::Subtraction => {
-				if let Token::Int(i) = token.0 {
-					expr = Some(Expression::Int(-i));
-				}
+			if op == Token::Power && power < prec {
+				break;
 			}
-			Token::FunctionName(f) => {
-				let t = &lexer.next().unwrap();
-				if t.0 != Token::LParen {
-					error(
-						self.name,
-						self.file,
-						"0001",
-						&format!("expected `(` found {}", t.0.as_string()),
-						"use `(` here",
-						&(t.1 - 1..t.1 + t.0.len() - 1),
-					);
-				};
-
-				let mut tokens = vec![];
-				let mut depth = 1;
-
-				loop {
-					let t = lexer.next();
 
-					if t.is_none() {
-						break;
-					}
-					let t = t.unwrap();
-
-					if t.0 == Token::LParen {
-						depth += 1;
-					} else if t.0 == Token::RParen {
-						depth -= 1;
-					}
+			if op != Token::Power && power <= prec {
+				break;
+			}
 
-					tokens.push(t);
+			self.next();
+			let rhs = self.expression(power)?;
+			expr = Expression::BinaryOperation(Box::new(expr), op, Box::new(rhs));
+		}
 
-					if depth == 0 {
-						break;
-					}
-				}
+		Ok(expr)
+	}
 
-				let mut params = vec![];
-				let mut expression: Tokens = vec![];
+	fn primary(&mut self) -> Result<Expression, Error> {
+		let (token, span) = match self.peek() {
+			Some(token) => token.clone(),
+			None => return Err(self.unexpected("an expression")),
+		};
 
-				for token in tokens {
-					if token.0 == Token::RParen {
-						if !expression.is_empty() {
-							let lex = expression.iter().collect::<Vec<_>>().into_iter().peekable();
-							let (data, _) = self.pratt_parser(lex, 0);
+		match token {
+			Token::LCurly => return self.block_expression(),
+			Token::Identifier(_)
+			| Token::FunctionName(_)
+			| Token::Bool(_)
+			| Token::Str(_)
+			| Token::Int(_)
+			| Token::Float(_)
+			| Token::Subtraction
+			| Token::Not
+			| Token::LParen
+			| Token::LSquare => {}
+			token => {
+				return Err(Error::new(
+					"0003",
+					"could not parse expression",
+					&format!("expected an expression found `{}`", token.as_string()),
+					span.range,
+				))
+			}
+		}
+		self.next();
 
-							params.push(data);
-						}
-						break;
-					}
+		Ok(match token {
+			Token::Identifier(ident) => {
+				if self.peek_is(&Token::LSquare) {
+					self.next();
+					let index = self.expression(0)?;
 
-					if token.0 == Token::Comma {
-						let lex = expression.iter().collect::<Vec<_>>().into_iter().peekable();
-						let (data, _) = self.pratt_parser(lex, 0);
+					if self.peek_is(&Token::Range) {
+						self.next();
+						let end = self.expression(0)?;
+						self.expect(Token::RSquare)?;
 
-						params.push(data);
+						Expression::VecSlice(ident, Box::new(index), Box::new(end))
+					} else {
+						self.expect(Token::RSquare)?;
 
-						expression.clear();
-						continue;
+						Expression::VecIndex(ident, Box::new(index))
 					}
-
-					expression.push(token.to_owned());
+				} else {
+					Expression::Identifier(ident)
 				}
+			}
+			Token::FunctionName(name) => {
+				self.expect(Token::LParen)?;
 
-				expr = Some(Expression::FunctionCall(f.to_string(), params));
+				Expression::FunctionCall(name, self.list(Token::RParen)?)
 			}
-			_ => {
-				if let Token::Int(i) = token.0 {
-					expr = Some(Expression::Int(i));
+			Token::Bool(bool) => Expression::Bool(bool),
+			Token::Str(str) => Expression::Str(str),
+			Token::Int(i) => Expression::Int(i),
+			Token::Float(f) => Expression::Float(f),
+			Token::Subtraction | Token::Not => {
+				let literal = matches!(self.peek(), Some((Token::Int(_) | Token::Float(_), _)));
+
+				// `^` binds tighter, so `-3 ^ 2` is `-(3 ^ 2)` like `-x ^ 2`
+				let power = self.infix_binding_power(&Token::Power).unwrap();
+				let operand = self.expression(power)?;
+
+				match operand {
+					Expression::Int(i) if literal && token == Token::Subtraction => Expression::Int(-i),
+					Expression::Float(f) if literal && token == Token::Subtraction => Expression::Float(-f),
+					operand => Expression::UnaryOperation(token, Box::new(operand)),
 				}
 			}
-		};
-
-		loop {
-			let op = lexer.peek();
-
-			if op.is_none() || op.unwrap().0 == Token::RParen {
-				lexer.next();
-				break;
-			}
+			Token::LParen => {
+				let expression = self.expression(0)?;
+				self.expect(Token::RParen)?;
 
-			if op.unwrap().0 == Token::Power && self.infix_binding_power(op.unwrap()) < prec {
-				break;
+				expression
 			}
+			Token::LSquare => {
+				let params = self.list(Token::RSquare)?;
+				let datatype = params.first().and_then(|param| param.infer_datatype());
 
-			if op.unwrap().0 != Token::Power && self.infix_binding_power(op.unwrap()) <= prec {
-				break;
+				Expression::Vector(params, datatype)
 			}
-			let op = lexer.next().unwrap();
-			let rhs;
-			(rhs, lexer) = self.pratt_parser(lexer, self.infix_binding_power(op));
-			expr = Some(Expression::BinaryOperation(
-				Box::new(expr.unwrap()),
-				op.0.clone(),
-				Box::new(rhs),
-			))
-		}
-
-		if expr.is_none() {
-			error(
-				self.name,
-				self.file,
-				"0003",
-				"could not parse expression",
-				"expression couldn't be parsed",
-				&(token.1..token.1),
-			);
-		}
-
-		(expr.unwrap(), lexer)
+			_ => unreachable!(),
+		})
 	}
 
-	fn infix_binding_power(&self, op: &(Token, usize)) -> u16 {
-		match op.0 {
-			Token::RCurly | Token::LCurly => 0,
-			Token::Addition => 1,
-			Token::Subtraction => 2,
-			Token::Multiplication => 3,
-			Token::Division => 4,
-			Token::Power => 5,
+	fn infix_binding_power(&self, op: &Token) -> Option<u16> {
+		Some(match op {
 			Token::IsNotEqual
 			| Token::IsEqual
 			| Token::IsGreater
 			| Token::IsLesser
 			| Token::IsGreaterEqual
-			| Token::IsLesserEqual => 6,
-			_ => {
-				error(
-					self.name,
-					self.file,
-					"0001",
-					&format!("expected an operator found {}", op.0.as_string()),
-					"use an operator here",
-					&(op.1..op.1 + op.0.len()),
-				);
-			}
+			| Token::IsLesserEqual => 1,
+			Token::Addition | Token::Subtraction => 2,
+			Token::Multiplication | Token::Division => 3,
+			Token::Power => 4,
+			_ => return None,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Parser;
+	use crate::{
+		ast::{AstNode, Expression},
+		lexer::Lexer,
+		token::Token,
+	};
+
+	fn expression(file: &str) -> Expression {
+		let tokens = Lexer::new(file).run().unwrap().to_vec();
+		Parser::new().run_expression(tokens).unwrap().0
+	}
+
+	#[test]
+	fn unary_minus_binds_looser_than_power() {
+		for file in ["-3 ^ 2", "-x ^ 2"] {
+			let Expression::UnaryOperation(Token::Subtraction, operand) = expression(file) else {
+				panic!("`{file}` is not a negation");
+			};
+			assert!(matches!(
+				*operand,
+				Expression::BinaryOperation(_, Token::Power, _)
+			));
 		}
+
+		assert!(matches!(
+			expression("(-3) ^ 2"),
+			Expression::BinaryOperation(lhs, Token::Power, _) if matches!(*lhs, Expression::Int(-3))
+		));
+	}
+
+	#[test]
+	fn unary_minus_binds_tighter_than_multiplication() {
+		assert!(matches!(
+			expression("-3 * 2"),
+			Expression::BinaryOperation(lhs, Token::Multiplication, _) if matches!(*lhs, Expression::Int(-3))
+		));
+		assert!(matches!(
+			expression("2 * -x"),
+			Expression::BinaryOperation(_, Token::Multiplication, rhs)
+				if matches!(*rhs, Expression::UnaryOperation(Token::Subtraction, _))
+		));
+	}
+
+	#[test]
+	fn not() {
+		assert!(matches!(
+			expression("!a == b"),
+			Expression::BinaryOperation(lhs, Token::IsEqual, _)
+				if matches!(*lhs, Expression::UnaryOperation(Token::Not, _))
+		));
+	}
+
+	#[test]
+	fn block_statements() {
+		let Expression::Block(ast, value) = expression("{ a = 1; v[0] = a; f(a); a }") else {
+			panic!("not a block");
+		};
+
+		assert!(matches!(ast[0].0, AstNode::ReAssignment(..)));
+		assert!(matches!(ast[1].0, AstNode::VecReAssignment(..)));
+		assert!(matches!(ast[2].0, AstNode::FunctionCall(..)));
+		assert!(matches!(*value, Expression::Identifier(_)));
 	}
 }
@@ -0,0 +1,194 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	ops::Range,
+	time::{Duration, Instant},
+};
+
+use crate::{
+	ast::{Ast, AstNode},
+	interpreter::{Hook, Interpreter},
+};
+
+/// Counts how often each line and function is run and how long functions
+/// take, for `--profile` and `--coverage`.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+	file: String,
+	/// Byte offset of the start of every line.
+	lines: Vec<usize>,
+	hits: BTreeMap<usize, usize>,
+	/// Line each function is declared on.
+	declarations: BTreeMap<String, usize>,
+	/// Calls and cumulative time of every function called.
+	functions: HashMap<String, (usize, Duration)>,
+	stack: Vec<(String, Instant)>,
+}
+
+impl Profiler {
+	/// Creates a profiler for `ast` parsed from `file`, every statement in it
+	/// starts with zero hits.
+	pub fn new(file: &str, ast: &Ast) -> Self {
+		let lines = std::iter::once(0)
+			.chain(file.match_indices('\n').map(|(i, _)| i + 1))
+			.collect();
+
+		let mut profiler = Self {
+			file: file.to_string(),
+			lines,
+			hits: BTreeMap::new(),
+			declarations: BTreeMap::new(),
+			functions: HashMap::new(),
+			stack: vec![],
+		};
+		profiler.collect(ast);
+
+		profiler
+	}
+
+	fn collect(&mut self, ast: &Ast) {
+		for (node, range) in ast {
+			let line = self.line(range.start);
+
+			match node {
+				AstNode::Test(..) => continue,
+				AstNode::FunctionDeclaration(name, _, _, body) => {
+					self.declarations.insert(name.clone(), line);
+					self.collect(body);
+				}
+				AstNode::If(_, body) | AstNode::Loop(body) => self.collect(body),
+				AstNode::IfElse(_, then, otherwise) => {
+					self.collect(then);
+					self.collect(otherwise);
+				}
+				_ => {}
+			}
+
+			self.hits.entry(line).or_insert(0);
+		}
+	}
+
+	/// The 1-based line `offset` is on.
+	fn line(&self, offset: usize) -> usize {
+		self.lines.partition_point(|start| *start <= offset)
+	}
+
+	/// Formats call counts and times of functions, slowest first, and the hits
+	/// of every line.
+	pub fn report(&self) -> String {
+		let mut functions = self.functions.iter().collect::<Vec<_>>();
+		functions.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+
+		let width = functions
+			.iter()
+			.map(|(name, _)| name.len())
+			.max()
+			.unwrap_or(0)
+			.max("function".len());
+
+		let mut report = format!(
+			"PROFILE\n\n{:<width$}  {:>8}  {:>12}\n",
+			"function", "calls", "time"
+		);
+		for (name, (calls, time)) in functions {
+			report += &format!("{name:<width$}  {calls:>8}  {:>12}\n", format!("{time:?}"));
+		}
+
+		report += &format!("\n{:>8}  {:>4}\n", "hits", "line");
+		for (line, hits) in &self.hits {
+			let text = self.file.lines().nth(line - 1).unwrap_or_default();
+			report += &format!("{hits:>8}  {line:>4} │ {text}\n");
+		}
+
+		report
+	}
+
+	/// Formats the coverage of `name` as an lcov tracefile.
+	pub fn lcov(&self, name: &str) -> String {
+		let mut lcov = format!("TN:\nSF:{name}\n");
+
+		for (function, line) in &self.declarations {
+			lcov += &format!("FN:{line},{function}\n");
+		}
+		for function in self.declarations.keys() {
+			let calls = self.functions.get(function).map_or(0, |(calls, _)| *calls);
+			lcov += &format!("FNDA:{calls},{function}\n");
+		}
+		lcov += &format!("FNF:{}\n", self.declarations.len());
+		lcov += &format!(
+			"FNH:{}\n",
+			self
+				.declarations
+				.keys()
+				.filter(|function| self.functions.contains_key(*function))
+				.count()
+		);
+
+		for (line, hits) in &self.hits {
+			lcov += &format!("DA:{line},{hits}\n");
+		}
+		lcov += &format!("LF:{}\n", self.hits.len());
+		lcov += &format!(
+			"LH:{}\n",
+			self.hits.values().filter(|hits| **hits > 0).count()
+		);
+
+		lcov + "end_of_record\n"
+	}
+}
+
+impl Hook for Profiler {
+	fn statement(&mut self, _: &Interpreter, range: &Range<usize>) {
+		let line = self.line(range.start);
+		*self.hits.entry(line).or_insert(0) += 1;
+	}
+
+	fn enter(&mut self, name: &str) {
+		self.functions.entry(name.to_string()).or_default().0 += 1;
+		self.stack.push((name.to_string(), Instant::now()));
+	}
+
+	fn exit(&mut self, name: &str) {
+		let Some((_, start)) = self.stack.pop() else {
+			return;
+		};
+
+		// only the outermost call of a recursive function is timed, the inner
+		// calls are part of its time already
+		if self.stack.iter().all(|(function, _)| function != name) {
+			self.functions.entry(name.to_string()).or_default().1 += start.elapsed();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Profiler;
+	use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser};
+	use std::{cell::RefCell, rc::Rc};
+
+	#[test]
+	fn lcov() {
+		let file = "fn double(x: int) -> int {\n\treturn x * 2;\n}\nfn unused() {\n\tprintln(0);\n}\nlet i: int = 0;\nloop {\n\ti = double(i + 1);\n\tif i > 5 { break; }\n}\n";
+		let tokens = Lexer::new(file).run().unwrap().to_vec();
+		let (ast, errors) = Parser::new().run(tokens);
+		assert!(errors.is_empty(), "{errors:?}");
+
+		let profiler = Rc::new(RefCell::new(Profiler::new(file, &ast)));
+		let mut interpreter = Interpreter::new();
+		interpreter.set_hook(profiler.clone());
+		interpreter.run(ast).unwrap();
+
+		// both statements on line 10 count, the `break` as well as the `if`
+		let lcov = profiler.borrow().lcov("main.oxi");
+		assert_eq!(
+			lcov,
+			"TN:\nSF:main.oxi\n\
+			FN:1,double\nFN:4,unused\n\
+			FNDA:2,double\nFNDA:0,unused\n\
+			FNF:2\nFNH:1\n\
+			DA:1,1\nDA:2,2\nDA:4,1\nDA:5,0\nDA:7,1\nDA:8,1\nDA:9,2\nDA:10,3\n\
+			LF:8\nLH:7\n\
+			end_of_record\n"
+		);
+	}
+}
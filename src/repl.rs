@@ -0,0 +1,455 @@
+use std::{fs, path::PathBuf, process, time::Instant};
+
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+
+use crate::{
+	ast::Expression,
+	data::DataType,
+	error::{self, Error},
+	helper::OxidoHelper,
+	interpreter::Interpreter,
+	lexer::Lexer,
+	parser::Parser,
+	sandbox::Cancel,
+	version,
+};
+
+const PROMPT: &str = "\x1b[1m\x1b[32m[In]:\x1b[0m ";
+const CONTINUATION: &str = "\x1b[1m\x1b[32m  ...\x1b[0m ";
+const OUT: &str = "\x1b[1m\x1b[31m[Out]:\x1b[0m ";
+
+const HELP: &str = "\
+:vars           list declared variables
+:fns            list declared functions
+:type <expr>    print the type of an expression without changing anything
+:ast <code>     print the syntax tree of some code
+:tokens <code>  print the tokens of some code
+:load <file>    run a file in this session
+:save <file>    write the statements run in this session to a file
+:reset          forget every variable and function
+:time           toggle printing the time each input takes
+:help           print this message";
+
+pub const COMMANDS: [&str; 10] = [
+	":vars", ":fns", ":type", ":ast", ":tokens", ":load", ":save", ":reset", ":time", ":help",
+];
+
+#[derive(Debug, Default)]
+struct Repl {
+	interpreter: Interpreter,
+	/// Stops the input being run when CTRL+C is pressed.
+	cancel: Cancel,
+	/// Every statement which ran without errors, for `:save`.
+	session: Vec<String>,
+	time: bool,
+}
+
+pub fn run() {
+	println!(
+		"Welcome to Oxido v{}\nTo exit, press CTRL+C or CTRL+D, for help type `:help`",
+		version()
+	);
+
+	let mut rl = Editor::<OxidoHelper, DefaultHistory>::new().unwrap();
+	rl.set_helper(Some(OxidoHelper::default()));
+
+	let history = history();
+	if let Some(history) = &history {
+		let _ = rl.load_history(history);
+	}
+
+	let mut repl = Repl::default();
+	repl.interpreter.set_cancel(repl.cancel.clone());
+
+	// while an input runs the terminal is not in raw mode, so CTRL+C sends
+	// a signal instead of reaching the editor
+	let cancel = repl.cancel.clone();
+	let _ = ctrlc::set_handler(move || cancel.cancel());
+
+	rl.helper_mut().unwrap().names = repl.names();
+	let mut input = String::new();
+
+	loop {
+		let prompt = if input.is_empty() {
+			PROMPT
+		} else {
+			CONTINUATION
+		};
+
+		match rl.readline(prompt) {
+			Ok(line) => {
+				if input.is_empty() && line.trim() == "exit" {
+					break;
+				}
+
+				if input.is_empty() && line.trim_start().starts_with(':') {
+					let _ = rl.add_history_entry(line.trim());
+					repl.command(line.trim());
+					rl.helper_mut().unwrap().names = repl.names();
+					continue;
+				}
+
+				input += &line;
+				input.push('\n');
+
+				if !balanced(&input) {
+					continue;
+				}
+
+				if !input.trim().is_empty() {
+					let _ = rl.add_history_entry(input.trim_end());
+					repl.eval("REPL", &input);
+					rl.helper_mut().unwrap().names = repl.names();
+				}
+
+				input.clear();
+
+				if repl.interpreter.exited() {
+					break;
+				}
+			}
+			Err(ReadlineError::Interrupted) if !input.is_empty() => input.clear(),
+			Err(ReadlineError::Interrupted) => {
+				println!("CTRL-C");
+				break;
+			}
+			Err(ReadlineError::Eof) => {
+				println!("CTRL-D");
+				break;
+			}
+			Err(err) => {
+				println!("Error: {:?}", err);
+				break;
+			}
+		}
+	}
+
+	if let Some(history) = &history {
+		let _ = rl.save_history(history);
+	}
+
+	if repl.interpreter.exited() {
+		process::exit(repl.interpreter.exit_code());
+	}
+}
+
+/// Path of the history file in the user's config directory.
+fn history() -> Option<PathBuf> {
+	let dir = dirs::config_dir()?.join("oxido");
+	fs::create_dir_all(&dir).ok()?;
+	Some(dir.join("history"))
+}
+
+impl Repl {
+	/// Names of every variable and function declared in the session, and of
+	/// the functions of the library.
+	fn names(&self) -> Vec<String> {
+		let variables = self.interpreter.variables().keys().map(String::as_str);
+		let functions = self.interpreter.functions().keys().map(String::as_str);
+		let library = self.interpreter.library().names();
+
+		variables
+			.chain(functions)
+			.chain(library)
+			.map(String::from)
+			.collect()
+	}
+
+	/// Runs `input`, reporting errors under `name` and timing it if enabled.
+	fn eval(&mut self, name: &str, input: &str) {
+		let start = Instant::now();
+
+		match self.run(input) {
+			Ok(true) => self.session.push(input.trim_end().to_string()),
+			Ok(false) => {}
+			Err(errors) => error::report(name, input, &errors),
+		}
+
+		if self.time {
+			println!("TIME: {:?}", start.elapsed());
+		}
+	}
+
+	/// Runs `input` against the interpreter, echoing the value when the input
+	/// is a bare expression, returns whether `input` was a list of statements.
+	fn run(&mut self, input: &str) -> Result<bool, Vec<Error>> {
+		let tokens = Lexer::new(input).run().map_err(|e| vec![e])?.to_vec();
+
+		if let Ok((expression, range)) = Parser::new().run_expression(tokens.clone()) {
+			if let Some(data) = self
+				.interpreter
+				.evaluate(expression, &range)
+				.map_err(|e| vec![e])?
+			{
+				println!("{OUT}{} : {}", data.as_string(), data.r#type());
+			}
+			return Ok(false);
+		}
+
+		let (ast, errors) = Parser::new().run(tokens);
+		if !errors.is_empty() {
+			return Err(errors);
+		}
+
+		self.interpreter.run(ast).map_err(|e| vec![e])?;
+		Ok(true)
+	}
+
+	/// Describes the type of the expression `input` for `:type`. A call of a
+	/// function of the session has the type it was declared with, anything
+	/// else is run in a copy of the session if it cannot change anything
+	/// outside of it.
+	fn type_of(&self, input: &str) -> Result<String, Error> {
+		let tokens = Lexer::new(input).run()?.to_vec();
+		let (expression, range) = Parser::new().run_expression(tokens)?;
+
+		if let Expression::FunctionCall(name, _) = &expression {
+			if let Some(function) = self.interpreter.functions().get(name) {
+				return Ok(
+					function
+						.datatype
+						.as_ref()
+						.map_or(String::from("expression has no value"), DataType::to_string),
+				);
+			}
+		}
+		if let Some(what) = self.effects(&expression) {
+			return Ok(format!("cannot find the type without running {what}"));
+		}
+
+		Ok(
+			match self.interpreter.clone().evaluate(expression, &range)? {
+				Some(data) => data.r#type().to_string(),
+				None => String::from("expression has no value"),
+			},
+		)
+	}
+
+	/// The first part of `expression` which may have side effects when run:
+	/// calls of functions of the session or of library functions with
+	/// effects, and blocks.
+	fn effects(&self, expression: &Expression) -> Option<String> {
+		match expression {
+			Expression::FunctionCall(name, params) => {
+				if self.interpreter.functions().contains_key(name)
+					|| self.interpreter.library().has_effects(name)
+				{
+					return Some(format!("`{name}`, which may have side effects"));
+				}
+				params.iter().find_map(|param| self.effects(param))
+			}
+			Expression::Block(..) => Some(String::from("a block")),
+			Expression::BinaryOperation(lhs, _, rhs) => self.effects(lhs).or_else(|| self.effects(rhs)),
+			Expression::UnaryOperation(_, expression) => self.effects(expression),
+			Expression::Vector(items, _) => items.iter().find_map(|item| self.effects(item)),
+			Expression::VecIndex(_, index) => self.effects(index),
+			Expression::VecSlice(_, start, end) => self.effects(start).or_else(|| self.effects(end)),
+			Expression::Str(_)
+			| Expression::Int(_)
+			| Expression::Float(_)
+			| Expression::Bool(_)
+			| Expression::Identifier(_) => None,
+		}
+	}
+
+	fn command(&mut self, line: &str) {
+		let (command, argument) = match line.split_once(char::is_whitespace) {
+			Some((command, argument)) => (command, argument.trim()),
+			None => (line, ""),
+		};
+
+		match command {
+			":vars" => {
+				let mut variables = self.interpreter.variables().iter().collect::<Vec<_>>();
+				variables.sort_by_key(|(name, _)| *name);
+
+				for (name, variable) in variables {
+					println!(
+						"{name}: {} = {}",
+						variable.datatype,
+						variable.data.as_string()
+					);
+				}
+			}
+			":fns" => {
+				let mut functions = self.interpreter.functions().values().collect::<Vec<_>>();
+				functions.sort_by_key(|function| &function.name);
+
+				for function in functions {
+					println!("{function}");
+				}
+			}
+			":type" => match self.type_of(argument) {
+				Ok(datatype) => println!("{datatype}"),
+				Err(e) => error::report("REPL", argument, &[e]),
+			},
+			":ast" => {
+				let tokens = match Lexer::new(argument).run() {
+					Ok(tokens) => tokens.to_vec(),
+					Err(e) => return error::report("REPL", argument, &[e]),
+				};
+				let (ast, errors) = Parser::new().run(tokens);
+
+				println!("{ast:#?}");
+				error::report("REPL", argument, &errors);
+			}
+			":tokens" => match Lexer::new(argument).run() {
+				Ok(tokens) => {
+					for (token, span) in tokens {
+						println!("{}:{} {token:?}", span.line, span.column);
+					}
+				}
+				Err(e) => error::report("REPL", argument, &[e]),
+			},
+			":load" => match fs::read_to_string(argument) {
+				Ok(contents) => self.eval(argument, &contents),
+				Err(e) => println!("error while reading `{argument}`, {e}"),
+			},
+			":save" => match fs::write(argument, self.session.join("\n") + "\n") {
+				Ok(()) => println!("saved {} entries to `{argument}`", self.session.len()),
+				Err(e) => println!("error while writing `{argument}`, {e}"),
+			},
+			":reset" => {
+				self.interpreter = Interpreter::new();
+				self.interpreter.set_cancel(self.cancel.clone());
+				self.session.clear();
+			}
+			":time" => {
+				self.time = !self.time;
+				println!("timing {}", if self.time { "on" } else { "off" });
+			}
+			":help" => println!("{HELP}"),
+			_ => println!("unknown command `{command}`, type `:help` for a list of commands"),
+		}
+	}
+}
+
+/// Whether every bracket in `input` is closed and no string is left open,
+/// used to decide if more lines are needed before evaluating.
+pub fn balanced(input: &str) -> bool {
+	let mut depth = 0;
+	let mut string = false;
+	let mut escaped = false;
+	let mut chars = input.chars().peekable();
+
+	while let Some(ch) = chars.next() {
+		if string {
+			match ch {
+				_ if escaped => escaped = false,
+				'\\' => escaped = true,
+				'"' => string = false,
+				_ => {}
+			}
+			continue;
+		}
+
+		match ch {
+			'"' => string = true,
+			'/' if chars.peek() == Some(&'/') => while chars.next_if(|ch| *ch != '\n').is_some() {},
+			'(' | '[' | '{' => depth += 1,
+			')' | ']' | '}' => depth -= 1,
+			_ => {}
+		}
+	}
+
+	!string && depth <= 0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{balanced, Repl};
+	use crate::data::Data;
+
+	#[test]
+	fn balanced_input() {
+		assert!(balanced("let a: int = 1;"));
+		assert!(balanced("fn f() {\n\treturn 1;\n}"));
+		assert!(!balanced("fn f() {"));
+		assert!(!balanced("if a {\n\tprintln([1, 2"));
+		assert!(!balanced("println(\"unterminated"));
+	}
+
+	#[test]
+	fn braces_in_strings_and_comments() {
+		assert!(balanced("println(\"{\");"));
+		assert!(balanced("println(\"}}\");"));
+		assert!(balanced("println(\"\\\" {\");"));
+		assert!(balanced("let a: int = 1; // {"));
+		assert!(!balanced("fn f() { // }"));
+		assert!(balanced("fn f() { // {\n}"));
+	}
+
+	fn value(repl: &Repl, name: &str) -> Option<Data> {
+		repl
+			.interpreter
+			.variables()
+			.get(name)
+			.map(|variable| variable.data.clone())
+	}
+
+	#[test]
+	fn state_persists_across_inputs() {
+		let mut repl = Repl::default();
+
+		assert_eq!(repl.run("let a: int = 2;").ok(), Some(true));
+		assert_eq!(
+			repl
+				.run("fn double(x: int) -> int {\n\treturn x * 2;\n}")
+				.ok(),
+			Some(true)
+		);
+		assert_eq!(repl.run("let b: int = double(a);").ok(), Some(true));
+		assert_eq!(value(&repl, "b"), Some(Data::Int(4)));
+
+		// an error leaves what was declared before it
+		assert!(repl.run("let c: int = 1 / 0;").is_err());
+		assert_eq!(value(&repl, "a"), Some(Data::Int(2)));
+		assert_eq!(value(&repl, "c"), None);
+
+		// expressions are echoed rather than recorded for `:save`
+		assert_eq!(repl.run("double(b)").ok(), Some(false));
+	}
+
+	#[test]
+	fn redeclaration() {
+		let mut repl = Repl::default();
+
+		repl.run("let a: int = 1;").unwrap();
+		repl.run("let a: str = \"one\";").unwrap();
+		assert_eq!(value(&repl, "a"), Some(Data::Str(String::from("one"))));
+
+		repl.run("fn f() -> int { return 1; }").unwrap();
+		repl.run("fn f() -> int { return 2; }").unwrap();
+		repl.run("let b: int = f();").unwrap();
+		assert_eq!(value(&repl, "b"), Some(Data::Int(2)));
+	}
+
+	#[test]
+	fn type_without_effects() {
+		let mut repl = Repl::default();
+		repl.run("let a: vec<int> = [1, 2];").unwrap();
+		repl
+			.run("fn f() -> str { println(\"ran\"); return \"x\"; }")
+			.unwrap();
+		repl
+			.run("fn g() { set_env(\"OXIDO_TYPE\", \"set\"); }")
+			.unwrap();
+
+		assert_eq!(repl.type_of("a[0] + 1").unwrap(), "int");
+		assert_eq!(repl.type_of("string::len(\"abc\")").unwrap(), "int");
+		assert_eq!(repl.type_of("f()").unwrap(), "str");
+		assert_eq!(repl.type_of("g()").unwrap(), "expression has no value");
+		assert_eq!(
+			repl.type_of("string::len(f())").unwrap(),
+			"cannot find the type without running `f`, which may have side effects"
+		);
+		assert_eq!(
+			repl.type_of("process::run(\"ls\", [\"-a\"])").unwrap(),
+			"cannot find the type without running `process::run`, which may have side effects"
+		);
+		assert!(repl.type_of("a[5]").is_err());
+		assert!(std::env::var("OXIDO_TYPE").is_err());
+	}
+}
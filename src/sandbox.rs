@@ -0,0 +1,146 @@
+use std::{
+	env,
+	path::{Component, Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+/// Operations a program may perform, everything is allowed by default.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+	/// Reading from stdin with `read`.
+	pub stdin: bool,
+	/// Writing to stdout with `print` and `println`.
+	pub stdout: bool,
+	/// Writing to stderr with `eprint` and `eprintln`.
+	pub stderr: bool,
+	/// Directories the `fs` module may access along with everything in them,
+	/// `None` allows every path.
+	pub paths: Option<Vec<PathBuf>>,
+	/// Reading and setting environment variables.
+	pub env: bool,
+	/// Starting processes with the `process` module.
+	pub process: bool,
+}
+
+impl Default for Capabilities {
+	fn default() -> Self {
+		Self {
+			stdin: true,
+			stdout: true,
+			stderr: true,
+			paths: None,
+			env: true,
+			process: true,
+		}
+	}
+}
+
+impl Capabilities {
+	/// Allows writing to stdout and stderr and nothing else, for running
+	/// untrusted programs.
+	pub fn sandboxed() -> Self {
+		Self {
+			stdin: false,
+			stdout: true,
+			stderr: true,
+			paths: Some(vec![]),
+			env: false,
+			process: false,
+		}
+	}
+
+	/// Whether the `fs` module may access `path`.
+	pub fn allows_path(&self, path: &str) -> bool {
+		let Some(paths) = &self.paths else {
+			return true;
+		};
+		let Some(path) = resolve(Path::new(path)) else {
+			return false;
+		};
+
+		paths
+			.iter()
+			.filter_map(|allowed| resolve(allowed))
+			.any(|allowed| path.starts_with(allowed))
+	}
+}
+
+/// Limits on the resources a program may use, only the depth of function
+/// calls is limited by default. Instructions and time are counted again for
+/// every call of [`Interpreter::run`](crate::interpreter::Interpreter::run).
+#[derive(Debug, Clone)]
+pub struct Limits {
+	/// Statements and loop iterations the program may run.
+	pub instructions: Option<u64>,
+	/// Bytes the values held in variables may take up, approximately. Only
+	/// values stored in variables and parameters are counted, but strings
+	/// joined with `+` and vectors or strings built by the standard library
+	/// are checked against what is left before they are built.
+	pub memory: Option<usize>,
+	/// Time the program may run for, counted from its first statement. It is
+	/// checked before every statement, so `read` and `process::run` can wait
+	/// past it and cannot be stopped with a [`Cancel`] while they do.
+	pub time: Option<Duration>,
+	/// Function calls that may be nested, so that deep recursion is an error
+	/// rather than overflowing the stack. The default of 500 fits in the
+	/// 8 MiB stack of a main thread, threads with smaller stacks need less.
+	pub depth: Option<usize>,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			instructions: None,
+			memory: None,
+			time: None,
+			depth: Some(500),
+		}
+	}
+}
+
+/// Stops an interpreter from another thread, it is checked before every
+/// statement and loop iteration.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+	/// Stops the program running, or the next one to run.
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	/// Whether the program is to be stopped, clearing it so that the next
+	/// program runs.
+	pub fn take(&self) -> bool {
+		self.0.swap(false, Ordering::Relaxed)
+	}
+}
+
+/// `path` made absolute with links resolved as far as it exists, so that it
+/// cannot leave a directory through `..` or a link.
+fn resolve(path: &Path) -> Option<PathBuf> {
+	let path = env::current_dir().ok()?.join(path);
+
+	let (mut resolved, rest) = path.ancestors().find_map(|ancestor| {
+		Some((
+			ancestor.canonicalize().ok()?,
+			path.strip_prefix(ancestor).ok()?,
+		))
+	})?;
+
+	for component in rest.components() {
+		match component {
+			Component::ParentDir => {
+				resolved.pop();
+			}
+			Component::Normal(name) => resolved.push(name),
+			_ => {}
+		}
+	}
+
+	Some(resolved)
+}
@@ -1,224 +1,1576 @@
-use std::ops::Range;
+use std::{
+	cell::RefCell,
+	collections::HashMap,
+	fmt::{self, Debug},
+	io::{Read, Write},
+	mem,
+	ops::Range,
+	rc::Rc,
+};
 
-use crate::data::Data;
+use crate::{
+	data::{Data, DataType},
+	error::Error,
+	sandbox::Capabilities,
+};
+
+const BUILTINS: [&str; 76] = [
+	"print",
+	"println",
+	"eprint",
+	"eprintln",
+	"read",
+	"int",
+	"float",
+	"bool",
+	"str",
+	"vec",
+	"assert",
+	"assert_eq",
+	"args",
+	"env",
+	"has_env",
+	"set_env",
+	"exit_code",
+	"string::len",
+	"string::split",
+	"string::join",
+	"string::trim",
+	"string::upper",
+	"string::lower",
+	"string::contains",
+	"string::starts_with",
+	"string::ends_with",
+	"string::replace",
+	"string::find",
+	"string::substring",
+	"string::repeat",
+	"string::chars",
+	"vector::len",
+	"vector::contains",
+	"vector::index_of",
+	"vector::slice",
+	"vector::concat",
+	"vector::range",
+	"vector::push",
+	"vector::pop",
+	"vector::insert",
+	"vector::remove",
+	"vector::reverse",
+	"vector::sort",
+	"math::abs",
+	"math::min",
+	"math::max",
+	"math::clamp",
+	"math::sqrt",
+	"math::floor",
+	"math::ceil",
+	"math::round",
+	"math::gcd",
+	"math::lcm",
+	"math::sin",
+	"math::cos",
+	"math::tan",
+	"math::asin",
+	"math::acos",
+	"math::atan",
+	"math::atan2",
+	"math::ln",
+	"math::log",
+	"math::log2",
+	"math::log10",
+	"fs::read_file",
+	"fs::write_file",
+	"fs::append_file",
+	"fs::read_lines",
+	"fs::exists",
+	"fs::list_dir",
+	"fs::mkdir",
+	"fs::remove",
+	"fs::join",
+	"process::run",
+	"process::run_input",
+	"process::spawn",
+];
+
+/// Types of the parameters of a registered function and of the value it
+/// returns, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+	pub params: Vec<DataType>,
+	pub returns: Option<DataType>,
+}
+
+impl Signature {
+	pub fn new(params: Vec<DataType>, returns: Option<DataType>) -> Self {
+		Self { params, returns }
+	}
+}
+
+/// Body of a function registered by the program embedding Oxido, an error
+/// it returns is reported as the function failing.
+pub type NativeFn = dyn Fn(Vec<Data>) -> Result<Option<Data>, String>;
+
+/// A function registered with [`StandardLibrary::register_fn`].
+#[derive(Clone)]
+struct Native {
+	signature: Signature,
+	function: Rc<NativeFn>,
+}
+
+impl Debug for Native {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Native")
+			.field("signature", &self.signature)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Where programs read input from and write output to.
+#[derive(Clone)]
+struct Streams {
+	stdin: Rc<RefCell<dyn Read>>,
+	stdout: Rc<RefCell<dyn Write>>,
+	stderr: Rc<RefCell<dyn Write>>,
+}
+
+impl Default for Streams {
+	fn default() -> Self {
+		#[cfg(not(target_arch = "wasm32"))]
+		let stdout: Rc<RefCell<dyn Write>> = Rc::new(RefCell::new(std::io::stdout()));
+		#[cfg(target_arch = "wasm32")]
+		let stdout: Rc<RefCell<dyn Write>> = Rc::new(RefCell::new(io::Console));
+
+		Self {
+			stdin: Rc::new(RefCell::new(std::io::stdin())),
+			stdout,
+			stderr: Rc::new(RefCell::new(std::io::stderr())),
+		}
+	}
+}
+
+impl Debug for Streams {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Streams").finish_non_exhaustive()
+	}
+}
 
 #[derive(Debug, Clone)]
-pub struct StandardLibrary<'a> {
-	name: &'a str,
-	file: String,
+pub struct StandardLibrary {
+	/// Status the program ends with, set by `exit_code` and `exit`.
+	code: i32,
+	capabilities: Capabilities,
+	natives: HashMap<String, Native>,
+	streams: Streams,
 }
 
-impl<'a> StandardLibrary<'a> {
-	pub fn new(name: &'a str, file: String) -> Self {
-		Self { name, file }
+impl Default for StandardLibrary {
+	fn default() -> Self {
+		Self::new(Capabilities::default())
+	}
+}
+
+impl StandardLibrary {
+	pub fn new(capabilities: Capabilities) -> Self {
+		Self {
+			code: 0,
+			capabilities,
+			natives: HashMap::new(),
+			streams: Streams::default(),
+		}
+	}
+
+	/// Sets where `read` reads from, stdin unless set.
+	pub fn set_stdin(&mut self, stdin: Rc<RefCell<dyn Read>>) {
+		self.streams.stdin = stdin;
+	}
+
+	/// Sets where `print` and `println` write to, stdout unless set.
+	pub fn set_stdout(&mut self, stdout: Rc<RefCell<dyn Write>>) {
+		self.streams.stdout = stdout;
+	}
+
+	/// Sets where `eprint` and `eprintln` write to, stderr unless set.
+	pub fn set_stderr(&mut self, stderr: Rc<RefCell<dyn Write>>) {
+		self.streams.stderr = stderr;
+	}
+
+	pub fn code(&self) -> i32 {
+		self.code
+	}
+
+	pub fn set_code(&mut self, code: i32) {
+		self.code = code;
+	}
+
+	/// Names of the functions, including those which were registered.
+	pub fn names(&self) -> Vec<&str> {
+		BUILTINS
+			.iter()
+			.copied()
+			.chain(self.natives.keys().map(String::as_str))
+			.collect()
+	}
+
+	/// Names of the constants, such as `math::PI`.
+	pub fn constants(&self) -> &'static [&'static str] {
+		&["math::PI", "math::E"]
+	}
+
+	pub fn constant(&self, x: &str) -> Option<Data> {
+		math::constant(x)
+	}
+
+	/// Whether calling `x` may do more than compute a value, such as printing,
+	/// changing files or starting processes. Registered functions may do
+	/// anything.
+	pub fn has_effects(&self, x: &str) -> bool {
+		self.natives.contains_key(x)
+			|| x.starts_with("process::")
+			|| matches!(
+				x,
+				"print"
+					| "println"
+					| "eprint"
+					| "eprintln"
+					| "read"
+					| "set_env"
+					| "exit_code"
+					| "fs::write_file"
+					| "fs::append_file"
+					| "fs::mkdir"
+					| "fs::remove"
+			)
 	}
+
+	/// Bytes that calling `x` with `params` adds, approximately, for the
+	/// functions which can build values much larger than their arguments, so
+	/// that the memory limit can be checked before they are built.
+	pub fn grows(&self, x: &str, params: &[Data]) -> usize {
+		match (x, params) {
+			("vector::range", [Data::Int(start), Data::Int(end)]) => {
+				(*start..*end).len() * mem::size_of::<Data>()
+			}
+			("string::repeat", [Data::Str(s), Data::Int(n)]) => {
+				s.len().saturating_mul((*n).max(0) as usize)
+			}
+			("vector::concat", [vec, other]) => vec.size() + other.size(),
+			("vector::push" | "vector::insert", [_, .., item]) => item.size(),
+			_ => 0,
+		}
+	}
+
 	pub fn contains(&self, x: &str) -> bool {
-		["print", "println", "read", "int", "bool", "str", "vec"].contains(&x)
+		self.natives.contains_key(x) || BUILTINS.contains(&x)
 	}
 
-	pub fn call(&self, x: &str, range: &Range<usize>, params: Vec<Data>) -> Option<Data> {
-		match x {
+	/// Makes `function` callable from programs as `name`, which may be
+	/// namespaced like `host::name`, replacing a function of the same name.
+	/// Arguments are checked against `signature` before it is called.
+	pub fn register_fn<F>(&mut self, name: &str, signature: Signature, function: F)
+	where
+		F: Fn(Vec<Data>) -> Result<Option<Data>, String> + 'static,
+	{
+		self.natives.insert(
+			name.to_string(),
+			Native {
+				signature,
+				function: Rc::new(function),
+			},
+		);
+	}
+
+	/// Signature of the registered function `x`.
+	pub fn signature(&self, x: &str) -> Option<&Signature> {
+		self.natives.get(x).map(|native| &native.signature)
+	}
+
+	fn call_native(
+		&self,
+		x: &str,
+		range: &Range<usize>,
+		params: Vec<Data>,
+	) -> Result<Option<Data>, Error> {
+		let native = &self.natives[x];
+		let signature = &native.signature;
+
+		if params.len() != signature.params.len() {
+			return Err(count(x, range, signature.params.len(), params.len()));
+		}
+		for (i, (data, datatype)) in params.iter().zip(&signature.params).enumerate() {
+			if data.r#type() != *datatype {
+				return Err(mismatch(x, range, i + 1, &datatype.to_string(), data));
+			}
+		}
+
+		let failed = |note: &str| Error::new("0018", &format!("`{x}` failed"), note, range.clone());
+
+		let data = (native.function)(params).map_err(|message| failed(&message))?;
+		if data.as_ref().map(Data::r#type) != signature.returns {
+			return Err(failed(&format!(
+				"`{x}` returned a value which does not match its signature"
+			)));
+		}
+
+		Ok(data)
+	}
+
+	/// Whether `x` changes the vector passed as its first argument, which has
+	/// to be called through [`StandardLibrary::call_mut`].
+	pub fn mutates(&self, x: &str) -> bool {
+		vector::MUTATING.contains(&x)
+	}
+
+	/// Calls a function which changes the vector passed first, returns the
+	/// value it returned if any and the changed vector.
+	pub fn call_mut(
+		&self,
+		x: &str,
+		range: &Range<usize>,
+		params: Vec<Data>,
+	) -> Result<(Option<Data>, Data), Error> {
+		vector::call_mut(x, range, params)
+	}
+
+	/// Checks that the capabilities allow calling `x` with `params`.
+	fn allowed(&self, x: &str, range: &Range<usize>, params: &[Data]) -> Result<(), Error> {
+		let capabilities = &self.capabilities;
+
+		let (allowed, what) = match x {
+			"read" => (capabilities.stdin, "reading stdin"),
+			"print" | "println" => (capabilities.stdout, "writing to stdout"),
+			"eprint" | "eprintln" => (capabilities.stderr, "writing to stderr"),
+			"env" | "has_env" | "set_env" => (capabilities.env, "using environment variables"),
+			_ if x.starts_with("process::") => (capabilities.process, "starting processes"),
+			"fs::join" => (true, ""),
+			_ if x.starts_with("fs::") => match params.first() {
+				Some(Data::Str(path)) if !capabilities.allows_path(path) => {
+					return Err(Error::new(
+						"0015",
+						&format!("access to `{path}` is not allowed"),
+						&format!(
+							"`{x}` may only access the directories allowed by the program running this interpreter"
+						),
+						range.clone(),
+					))
+				}
+				_ => (true, ""),
+			},
+			_ => (true, ""),
+		};
+
+		if allowed {
+			return Ok(());
+		}
+
+		Err(Error::new(
+			"0015",
+			&format!("{what} is not allowed"),
+			&format!("`{x}` was disabled by the program running this interpreter"),
+			range.clone(),
+		))
+	}
+
+	pub fn call(
+		&mut self,
+		x: &str,
+		range: &Range<usize>,
+		params: Vec<Data>,
+	) -> Result<Option<Data>, Error> {
+		self.allowed(x, range, &params)?;
+
+		if self.natives.contains_key(x) {
+			return self.call_native(x, range, params);
+		}
+
+		Ok(match x {
 			"print" => {
-				io::print(params);
+				io::write(range, "stdout", &self.streams.stdout, params, "")?;
 				None
 			}
 			"println" => {
-				io::println(params);
+				io::write(range, "stdout", &self.streams.stdout, params, "\n")?;
 				None
 			}
-			"read" => Some(io::read()),
-			"int" => Some(types::int(
-				self.name,
-				&self.file,
-				range,
-				params.first()?.to_owned(),
-			)),
-			"bool" => Some(types::bool(
-				self.name,
-				&self.file,
-				range,
-				params.first()?.to_owned(),
-			)),
-			"str" => Some(types::str(
-				self.name,
-				&self.file,
-				range,
-				params.first()?.to_owned(),
-			)),
-			"vec" => Some(types::vec(
-				self.name,
-				&self.file,
-				range,
-				params.first()?.to_owned(),
-			)),
+			"eprint" => {
+				io::write(range, "stderr", &self.streams.stderr, params, "")?;
+				None
+			}
+			"eprintln" => {
+				io::write(range, "stderr", &self.streams.stderr, params, "\n")?;
+				None
+			}
+			"read" => Some(io::read(range, &self.streams.stdin)?),
+			"int" => Some(types::int(range, first(x, range, params)?)?),
+			"float" => Some(types::float(range, first(x, range, params)?)?),
+			"bool" => Some(types::bool(range, first(x, range, params)?)?),
+			"str" => Some(types::str(range, first(x, range, params)?)?),
+			"vec" => Some(types::vec(range, first(x, range, params)?)?),
+			"assert" => {
+				test::assert(range, first(x, range, params)?)?;
+				None
+			}
+			"assert_eq" => {
+				test::assert_eq(range, params)?;
+				None
+			}
+			// replaced by `Interpreter::set_args` when there are arguments
+			"args" => {
+				signature(x, range, params, [])?;
+				Some(Data::Vector(vec![], DataType::Str))
+			}
+			"env" => match signature(x, range, params, [DataType::Str])? {
+				[Data::Str(name)] => match std::env::var(&name) {
+					Ok(value) => Some(Data::Str(value)),
+					Err(e) => {
+						let note = match e {
+							std::env::VarError::NotPresent => {
+								format!("`{name}` is not set, check with `has_env` first")
+							}
+							std::env::VarError::NotUnicode(_) => {
+								format!("the value of `{name}` is not valid unicode")
+							}
+						};
+						return Err(Error::new("0012", "invalid argument", &note, range.clone()));
+					}
+				},
+				_ => unreachable!(),
+			},
+			"has_env" => match signature(x, range, params, [DataType::Str])? {
+				[Data::Str(name)] => Some(Data::Bool(std::env::var_os(name).is_some())),
+				_ => unreachable!(),
+			},
+			"set_env" => match signature(x, range, params, [DataType::Str, DataType::Str])? {
+				[Data::Str(name), Data::Str(value)] => {
+					if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+						return Err(Error::new(
+							"0012",
+							"invalid argument",
+							&format!("`{name}` cannot be set as an environment variable"),
+							range.clone(),
+						));
+					}
+					std::env::set_var(name, value);
+					None
+				}
+				_ => unreachable!(),
+			},
+			"exit_code" => match signature(x, range, params, [DataType::Int])? {
+				[Data::Int(code)] => {
+					self.code = code;
+					None
+				}
+				_ => unreachable!(),
+			},
+			_ if x.starts_with("string::") => Some(string::call(x, range, params)?),
+			_ if x.starts_with("vector::") => Some(vector::call(x, range, params)?),
+			_ if x.starts_with("math::") => Some(math::call(x, range, params)?),
+			_ if x.starts_with("fs::") => fs::call(x, range, params)?,
+			_ if x.starts_with("process::") => {
+				// what `process::spawn` prints goes where the program's own
+				// output would, or nowhere if that is not allowed
+				let stdout = self.capabilities.stdout.then_some(&*self.streams.stdout);
+				let stderr = self.capabilities.stderr.then_some(&*self.streams.stderr);
+				Some(process::call(x, range, params, [stdout, stderr])?)
+			}
 			_ => panic!("not a global function"),
+		})
+	}
+}
+
+fn first(x: &str, range: &Range<usize>, params: Vec<Data>) -> Result<Data, Error> {
+	match params.into_iter().next() {
+		Some(data) => Ok(data),
+		None => Err(Error::new(
+			"0004",
+			"not enough arguments were passed",
+			&format!("`{x}` expects 1 argument but 0 were passed"),
+			range.clone(),
+		)),
+	}
+}
+
+fn count(x: &str, range: &Range<usize>, expected: usize, passed: usize) -> Error {
+	Error::new(
+		"0004",
+		"wrong number of arguments were passed",
+		&format!(
+			"`{x}` expects {expected} argument{} but {passed} were passed",
+			if expected == 1 { "" } else { "s" },
+		),
+		range.clone(),
+	)
+}
+
+/// Checks that `N` arguments were passed to `x` and returns them.
+fn arguments<const N: usize>(
+	x: &str,
+	range: &Range<usize>,
+	params: Vec<Data>,
+) -> Result<[Data; N], Error> {
+	<[Data; N]>::try_from(params).map_err(|params| count(x, range, N, params.len()))
+}
+
+fn mismatch(x: &str, range: &Range<usize>, i: usize, expected: &str, data: &Data) -> Error {
+	Error::new(
+		"0011",
+		"incorrect data type",
+		&format!("argument {i} of `{x}` expected `{expected}` found {data}"),
+		range.clone(),
+	)
+}
+
+/// Checks `params` against the types `x` expects and returns them, so
+/// misuse is reported before the function runs.
+fn signature<const N: usize>(
+	x: &str,
+	range: &Range<usize>,
+	params: Vec<Data>,
+	types: [DataType; N],
+) -> Result<[Data; N], Error> {
+	let params = arguments(x, range, params)?;
+
+	for (i, (data, datatype)) in params.iter().zip(types).enumerate() {
+		if data.r#type() != datatype {
+			return Err(mismatch(x, range, i + 1, &datatype.to_string(), data));
 		}
 	}
+
+	Ok(params)
+}
+
+fn overflow(range: &Range<usize>) -> Error {
+	Error::new(
+		"0007",
+		"integer overflow",
+		"the result does not fit in an `int`",
+		range.clone(),
+	)
+}
+
+/// A length or position as an `int`.
+fn int(range: &Range<usize>, i: usize) -> Result<Data, Error> {
+	i32::try_from(i).map(Data::Int).map_err(|_| overflow(range))
+}
+
+/// A `vec<str>` of `strs`.
+fn strs(strs: impl Iterator<Item = String>) -> Data {
+	Data::Vector(strs.map(Data::Str).collect(), DataType::Str)
 }
 
 mod types {
 	use crate::{
 		data::{Data, DataType},
-		error::error,
+		error::Error,
 	};
 	use std::ops::Range;
 
-	pub fn vec(name: &str, file: &str, range: &Range<usize>, data: Data) -> Data {
+	fn mismatch(range: &Range<usize>, expected: &str, data: Data) -> Error {
+		Error::new(
+			"0011",
+			"incorrect data type",
+			&format!("mismatched data types expected `{expected}` found {data}"),
+			range.clone(),
+		)
+	}
+
+	pub fn vec(range: &Range<usize>, data: Data) -> Result<Data, Error> {
 		match data {
-			Data::Vector(_, _) => data,
-			Data::Str(str) => Data::Vector(
+			Data::Vector(_, _) => Ok(data),
+			Data::Str(str) => Ok(Data::Vector(
 				str.chars().map(|ch| Data::Str(ch.to_string())).collect(),
 				DataType::Str,
-			),
-			_ => error(
-				name,
-				file,
-				"E00011",
-				"incorrect data type",
-				&format!("mismatched data types expected `vector` found {}", data),
-				range,
-			),
+			)),
+			_ => Err(mismatch(range, "vector", data)),
 		}
 	}
 
-	pub fn int(name: &str, file: &str, range: &Range<usize>, data: Data) -> Data {
+	pub fn int(range: &Range<usize>, data: Data) -> Result<Data, Error> {
 		match data {
-			Data::Int(_) => data,
-			Data::Bool(b) => Data::Int(b as i32),
-			Data::Str(s) => Data::Int(s.parse::<i32>().unwrap()),
-			_ => error(
-				name,
-				file,
-				"E00011",
-				"incorrect data type",
-				&format!(
-					"mismatched data types expected `int | bool | str` found {}",
-					data
-				),
-				range,
-			),
+			Data::Int(_) => Ok(data),
+			Data::Float(f) if f.is_finite() && f.trunc().abs() <= i32::MAX as f64 => {
+				Ok(Data::Int(f.trunc() as i32))
+			}
+			Data::Float(f) => Err(Error::new(
+				"0012",
+				"invalid conversion",
+				&format!("`{}` does not fit in an `int`", f),
+				range.clone(),
+			)),
+			Data::Bool(b) => Ok(Data::Int(b as i32)),
+			Data::Str(s) => match s.trim().parse::<i32>() {
+				Ok(i) => Ok(Data::Int(i)),
+				Err(_) => Err(Error::new(
+					"0012",
+					"invalid conversion",
+					&format!("`{s}` is not a valid `int`"),
+					range.clone(),
+				)),
+			},
+			_ => Err(mismatch(range, "int | float | bool | str", data)),
 		}
 	}
 
-	pub fn bool(name: &str, file: &str, range: &Range<usize>, data: Data) -> Data {
+	pub fn float(range: &Range<usize>, data: Data) -> Result<Data, Error> {
 		match data {
-			Data::Int(i) => Data::Bool(i != 0),
-			Data::Bool(_) => data,
-			Data::Str(s) => Data::Bool(s.parse::<bool>().unwrap()),
-			_ => error(
-				name,
-				file,
-				"E00011",
-				"incorrect data type",
-				&format!(
-					"mismatched data types expected `int | bool | str` found {}",
-					data
-				),
-				range,
-			),
+			Data::Int(i) => Ok(Data::Float(i as f64)),
+			Data::Float(_) => Ok(data),
+			Data::Str(s) => match s.trim().parse::<f64>() {
+				Ok(f) => Ok(Data::Float(f)),
+				Err(_) => Err(Error::new(
+					"0012",
+					"invalid conversion",
+					&format!("`{s}` is not a valid `float`"),
+					range.clone(),
+				)),
+			},
+			_ => Err(mismatch(range, "int | float | str", data)),
+		}
+	}
+
+	pub fn bool(range: &Range<usize>, data: Data) -> Result<Data, Error> {
+		match data {
+			Data::Int(i) => Ok(Data::Bool(i != 0)),
+			Data::Bool(_) => Ok(data),
+			Data::Str(s) => match s.trim().parse::<bool>() {
+				Ok(b) => Ok(Data::Bool(b)),
+				Err(_) => Err(Error::new(
+					"0012",
+					"invalid conversion",
+					&format!("`{s}` is not a valid `bool`"),
+					range.clone(),
+				)),
+			},
+			_ => Err(mismatch(range, "int | bool | str", data)),
+		}
+	}
+
+	pub fn str(range: &Range<usize>, data: Data) -> Result<Data, Error> {
+		match data {
+			Data::Int(i) => Ok(Data::Str(i.to_string())),
+			Data::Float(_) | Data::Bool(_) => Ok(Data::Str(data.as_string())),
+			Data::Str(_) => Ok(data),
+			_ => Err(mismatch(range, "int | float | bool | str", data)),
+		}
+	}
+}
+
+mod string {
+	use super::{int, signature, strs};
+	use crate::{
+		data::{Data, DataType},
+		error::Error,
+	};
+	use std::ops::Range;
+
+	/// Calls the `string::` function `x`, positions and lengths count
+	/// characters rather than bytes.
+	pub fn call(x: &str, range: &Range<usize>, params: Vec<Data>) -> Result<Data, Error> {
+		use DataType::{Int, Str};
+
+		Ok(match x {
+			"string::len" => match signature(x, range, params, [Str])? {
+				[Data::Str(s)] => int(range, s.chars().count())?,
+				_ => unreachable!(),
+			},
+			"string::split" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(s), Data::Str(separator)] => {
+					if separator.is_empty() {
+						return Err(Error::new(
+							"0012",
+							"invalid argument",
+							"the separator must not be empty, use `string::chars` instead",
+							range.clone(),
+						));
+					}
+					strs(s.split(&separator).map(str::to_string))
+				}
+				_ => unreachable!(),
+			},
+			"string::join" => {
+				match signature(x, range, params, [DataType::Vector(Box::new(Str)), Str])? {
+					[Data::Vector(strs, _), Data::Str(separator)] => Data::Str(
+						strs
+							.iter()
+							.map(Data::as_string)
+							.collect::<Vec<_>>()
+							.join(&separator),
+					),
+					_ => unreachable!(),
+				}
+			}
+			"string::trim" => match signature(x, range, params, [Str])? {
+				[Data::Str(s)] => Data::Str(s.trim().to_string()),
+				_ => unreachable!(),
+			},
+			"string::upper" => match signature(x, range, params, [Str])? {
+				[Data::Str(s)] => Data::Str(s.to_uppercase()),
+				_ => unreachable!(),
+			},
+			"string::lower" => match signature(x, range, params, [Str])? {
+				[Data::Str(s)] => Data::Str(s.to_lowercase()),
+				_ => unreachable!(),
+			},
+			"string::contains" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(s), Data::Str(pattern)] => Data::Bool(s.contains(&pattern)),
+				_ => unreachable!(),
+			},
+			"string::starts_with" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(s), Data::Str(pattern)] => Data::Bool(s.starts_with(&pattern)),
+				_ => unreachable!(),
+			},
+			"string::ends_with" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(s), Data::Str(pattern)] => Data::Bool(s.ends_with(&pattern)),
+				_ => unreachable!(),
+			},
+			"string::replace" => match signature(x, range, params, [Str, Str, Str])? {
+				[Data::Str(s), Data::Str(from), Data::Str(to)] => Data::Str(s.replace(&from, &to)),
+				_ => unreachable!(),
+			},
+			"string::find" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(s), Data::Str(pattern)] => match s.find(&pattern) {
+					Some(i) => int(range, s[..i].chars().count())?,
+					None => Data::Int(-1),
+				},
+				_ => unreachable!(),
+			},
+			"string::substring" => match signature(x, range, params, [Str, Int, Int])? {
+				[Data::Str(s), Data::Int(start), Data::Int(end)] => {
+					let len = s.chars().count();
+					if start < 0 || end < start || end as usize > len {
+						return Err(Error::new(
+							"0006",
+							"index out of bounds",
+							&format!("range {start}..{end} is out of bounds for string of length {len}"),
+							range.clone(),
+						));
+					}
+
+					Data::Str(
+						s.chars()
+							.skip(start as usize)
+							.take((end - start) as usize)
+							.collect(),
+					)
+				}
+				_ => unreachable!(),
+			},
+			"string::repeat" => match signature(x, range, params, [Str, Int])? {
+				[Data::Str(s), Data::Int(n)] => {
+					if n < 0 {
+						return Err(Error::new(
+							"0012",
+							"invalid argument",
+							&format!("cannot repeat a string {n} times"),
+							range.clone(),
+						));
+					}
+					Data::Str(s.repeat(n as usize))
+				}
+				_ => unreachable!(),
+			},
+			"string::chars" => match signature(x, range, params, [Str])? {
+				[Data::Str(s)] => strs(s.chars().map(String::from)),
+				_ => unreachable!(),
+			},
+			_ => panic!("not a string function"),
+		})
+	}
+}
+
+mod vector {
+	use super::{arguments, count, int, mismatch};
+	use crate::{
+		data::{Data, DataType},
+		error::Error,
+	};
+	use std::ops::Range;
+
+	pub const MUTATING: [&str; 6] = [
+		"vector::push",
+		"vector::pop",
+		"vector::insert",
+		"vector::remove",
+		"vector::reverse",
+		"vector::sort",
+	];
+
+	/// The elements and element type of argument `i`, which must be a vector.
+	fn vector(
+		x: &str,
+		range: &Range<usize>,
+		i: usize,
+		data: Data,
+	) -> Result<(Vec<Data>, DataType), Error> {
+		match data {
+			Data::Vector(vec, datatype) => Ok((vec, datatype)),
+			data => Err(mismatch(x, range, i, "vec", &data)),
 		}
 	}
 
-	pub fn str(name: &str, file: &str, range: &Range<usize>, data: Data) -> Data {
+	/// Checks that argument `i` can be an element of a vector of `datatype`.
+	fn element(
+		x: &str,
+		range: &Range<usize>,
+		i: usize,
+		datatype: &DataType,
+		data: &Data,
+	) -> Result<(), Error> {
+		if data.r#type() != *datatype {
+			return Err(mismatch(x, range, i, &datatype.to_string(), data));
+		}
+
+		Ok(())
+	}
+
+	/// Argument `i` as an index which is at most `max`.
+	fn index(
+		x: &str,
+		range: &Range<usize>,
+		i: usize,
+		data: Data,
+		max: usize,
+	) -> Result<usize, Error> {
 		match data {
-			Data::Int(i) => Data::Str(i.to_string()),
-			Data::Bool(b) => Data::Str(b.to_string()),
-			Data::Str(_) => data,
-			_ => error(
-				name,
-				file,
-				"E00011",
+			Data::Int(index) if index >= 0 && index as usize <= max => Ok(index as usize),
+			Data::Int(index) => Err(Error::new(
+				"0006",
+				"index out of bounds",
+				&format!("index {index} is out of bounds, the highest index allowed is {max}"),
+				range.clone(),
+			)),
+			data => Err(mismatch(x, range, i, "int", &data)),
+		}
+	}
+
+	fn empty(range: &Range<usize>) -> Error {
+		Error::new(
+			"0006",
+			"index out of bounds",
+			"the vector is empty",
+			range.clone(),
+		)
+	}
+
+	/// Calls the `vector::` function `x` which leaves its arguments as they
+	/// are.
+	pub fn call(x: &str, range: &Range<usize>, params: Vec<Data>) -> Result<Data, Error> {
+		Ok(match x {
+			"vector::len" => {
+				let [vec] = arguments(x, range, params)?;
+				int(range, vector(x, range, 1, vec)?.0.len())?
+			}
+			"vector::contains" | "vector::index_of" => {
+				let [vec, item] = arguments(x, range, params)?;
+				let (vec, datatype) = vector(x, range, 1, vec)?;
+				element(x, range, 2, &datatype, &item)?;
+
+				let position = vec.iter().position(|data| *data == item);
+				match x {
+					"vector::contains" => Data::Bool(position.is_some()),
+					_ => match position {
+						Some(i) => int(range, i)?,
+						None => Data::Int(-1),
+					},
+				}
+			}
+			"vector::slice" => {
+				let [vec, start, end] = arguments(x, range, params)?;
+				let (vec, datatype) = vector(x, range, 1, vec)?;
+				let end = index(x, range, 3, end, vec.len())?;
+				let start = index(x, range, 2, start, end)?;
+
+				Data::Vector(vec[start..end].to_vec(), datatype)
+			}
+			"vector::concat" => {
+				let [vec, other] = arguments(x, range, params)?;
+				let (mut vec, datatype) = vector(x, range, 1, vec)?;
+				let (other, other_datatype) = vector(x, range, 2, other)?;
+				if other_datatype != datatype {
+					return Err(mismatch(
+						x,
+						range,
+						2,
+						&DataType::Vector(Box::new(datatype)).to_string(),
+						&Data::Vector(other, other_datatype),
+					));
+				}
+
+				vec.extend(other);
+				Data::Vector(vec, datatype)
+			}
+			"vector::range" => match arguments(x, range, params)? {
+				[Data::Int(start), Data::Int(end)] => {
+					Data::Vector((start..end).map(Data::Int).collect(), DataType::Int)
+				}
+				[Data::Int(_), end] => return Err(mismatch(x, range, 2, "int", &end)),
+				[start, _] => return Err(mismatch(x, range, 1, "int", &start)),
+			},
+			_ => panic!("not a vector function"),
+		})
+	}
+
+	/// Calls the `vector::` function `x` which changes the vector passed first,
+	/// returns the value it returned if any and the changed vector.
+	pub fn call_mut(
+		x: &str,
+		range: &Range<usize>,
+		mut params: Vec<Data>,
+	) -> Result<(Option<Data>, Data), Error> {
+		let expected = match x {
+			"vector::push" | "vector::remove" => 2,
+			"vector::insert" => 3,
+			_ => 1,
+		};
+		if params.len() != expected {
+			return Err(count(x, range, expected, params.len()));
+		}
+		let (mut vec, datatype) = vector(x, range, 1, params.remove(0))?;
+
+		let data = match x {
+			"vector::push" => {
+				let [item] = arguments(x, range, params)?;
+				element(x, range, 2, &datatype, &item)?;
+				vec.push(item);
+				None
+			}
+			"vector::pop" => {
+				let [] = arguments(x, range, params)?;
+				Some(vec.pop().ok_or_else(|| empty(range))?)
+			}
+			"vector::insert" => {
+				let [i, item] = arguments(x, range, params)?;
+				let i = index(x, range, 2, i, vec.len())?;
+				element(x, range, 3, &datatype, &item)?;
+				vec.insert(i, item);
+				None
+			}
+			"vector::remove" => {
+				let [i] = arguments(x, range, params)?;
+				if vec.is_empty() {
+					return Err(empty(range));
+				}
+				let i = index(x, range, 2, i, vec.len() - 1)?;
+				Some(vec.remove(i))
+			}
+			"vector::reverse" => {
+				let [] = arguments(x, range, params)?;
+				vec.reverse();
+				None
+			}
+			"vector::sort" => {
+				let [] = arguments(x, range, params)?;
+				vec.sort_by(Data::total_cmp);
+				None
+			}
+			_ => panic!("not a mutating vector function"),
+		};
+
+		Ok((data, Data::Vector(vec, datatype)))
+	}
+}
+
+mod math {
+	use super::{arguments, mismatch, overflow};
+	use crate::{data::Data, error::Error};
+	use std::{f64::consts, ops::Range};
+
+	pub fn constant(x: &str) -> Option<Data> {
+		match x {
+			"math::PI" => Some(Data::Float(consts::PI)),
+			"math::E" => Some(Data::Float(consts::E)),
+			_ => None,
+		}
+	}
+
+	/// Argument `i` as a float, an `int` is widened to a `float`.
+	fn float(x: &str, range: &Range<usize>, i: usize, data: &Data) -> Result<f64, Error> {
+		match data {
+			Data::Int(n) => Ok(*n as f64),
+			Data::Float(f) => Ok(*f),
+			data => Err(mismatch(x, range, i, "int | float", data)),
+		}
+	}
+
+	fn int(x: &str, range: &Range<usize>, i: usize, data: &Data) -> Result<i32, Error> {
+		match data {
+			Data::Int(n) => Ok(*n),
+			data => Err(mismatch(x, range, i, "int", data)),
+		}
+	}
+
+	/// `f` rounded by `round` as an `int`.
+	fn rounded(range: &Range<usize>, f: f64, round: fn(f64) -> f64) -> Result<Data, Error> {
+		let f = round(f);
+
+		if f.is_finite() && f >= i32::MIN as f64 && f <= i32::MAX as f64 {
+			Ok(Data::Int(f as i32))
+		} else {
+			Err(overflow(range))
+		}
+	}
+
+	fn domain(range: &Range<usize>, note: &str) -> Error {
+		Error::new("0012", "argument out of domain", note, range.clone())
+	}
+
+	fn gcd(a: i32, b: i32) -> i64 {
+		let (mut a, mut b) = ((a as i64).abs(), (b as i64).abs());
+
+		while b != 0 {
+			(a, b) = (b, a % b);
+		}
+
+		a
+	}
+
+	/// Calls the `math::` function `x`, given only ints a function returns an
+	/// `int` where it can and otherwise a `float`.
+	pub fn call(x: &str, range: &Range<usize>, params: Vec<Data>) -> Result<Data, Error> {
+		Ok(match x {
+			"math::abs" => match arguments(x, range, params)? {
+				[Data::Int(n)] => Data::Int(n.checked_abs().ok_or_else(|| overflow(range))?),
+				[n] => Data::Float(float(x, range, 1, &n)?.abs()),
+			},
+			"math::min" | "math::max" => match arguments(x, range, params)? {
+				[Data::Int(a), Data::Int(b)] if x == "math::min" => Data::Int(a.min(b)),
+				[Data::Int(a), Data::Int(b)] => Data::Int(a.max(b)),
+				[a, b] => {
+					let (a, b) = (float(x, range, 1, &a)?, float(x, range, 2, &b)?);
+					Data::Float(if x == "math::min" { a.min(b) } else { a.max(b) })
+				}
+			},
+			"math::clamp" => match arguments(x, range, params)? {
+				[Data::Int(n), Data::Int(min), Data::Int(max)] => {
+					if min > max {
+						return Err(domain(
+							range,
+							&format!("the minimum {min} is greater than the maximum {max}"),
+						));
+					}
+					Data::Int(n.clamp(min, max))
+				}
+				[n, min, max] => {
+					let n = float(x, range, 1, &n)?;
+					let (min, max) = (float(x, range, 2, &min)?, float(x, range, 3, &max)?);
+					if min > max || min.is_nan() || max.is_nan() {
+						return Err(domain(
+							range,
+							&format!("the minimum {min} is greater than the maximum {max}"),
+						));
+					}
+					Data::Float(n.clamp(min, max))
+				}
+			},
+			"math::floor" | "math::ceil" | "math::round" => match arguments(x, range, params)? {
+				[Data::Int(n)] => Data::Int(n),
+				[n] => {
+					let n = float(x, range, 1, &n)?;
+					match x {
+						"math::floor" => rounded(range, n, f64::floor)?,
+						"math::ceil" => rounded(range, n, f64::ceil)?,
+						_ => rounded(range, n, f64::round)?,
+					}
+				}
+			},
+			"math::gcd" | "math::lcm" => {
+				let [a, b] = arguments(x, range, params)?;
+				let (a, b) = (int(x, range, 1, &a)?, int(x, range, 2, &b)?);
+
+				let result = match (x, gcd(a, b)) {
+					("math::gcd", gcd) => gcd,
+					(_, 0) => 0,
+					(_, gcd) => (a as i64 / gcd * b as i64).abs(),
+				};
+				Data::Int(i32::try_from(result).map_err(|_| overflow(range))?)
+			}
+			"math::atan2" | "math::log" => {
+				let [a, b] = arguments(x, range, params)?;
+				let (a, b) = (float(x, range, 1, &a)?, float(x, range, 2, &b)?);
+
+				if x == "math::atan2" {
+					Data::Float(a.atan2(b))
+				} else if a <= 0.0 {
+					return Err(domain(
+						range,
+						&format!("cannot take the logarithm of {a}, it must be positive"),
+					));
+				} else if b <= 0.0 || b == 1.0 {
+					return Err(domain(
+						range,
+						&format!("cannot take the logarithm to base {b}, it must be positive and not 1"),
+					));
+				} else {
+					Data::Float(a.log(b))
+				}
+			}
+			_ => {
+				let [n] = arguments(x, range, params)?;
+				let n = float(x, range, 1, &n)?;
+
+				Data::Float(match x {
+					"math::sqrt" if n < 0.0 => {
+						return Err(domain(
+							range,
+							&format!("cannot take the square root of {n}, it must not be negative"),
+						))
+					}
+					"math::sqrt" => n.sqrt(),
+					"math::sin" => n.sin(),
+					"math::cos" => n.cos(),
+					"math::tan" => n.tan(),
+					"math::asin" | "math::acos" if !(-1.0..=1.0).contains(&n) => {
+						return Err(domain(
+							range,
+							&format!("`{x}` of {n} is undefined, it must be between -1 and 1"),
+						))
+					}
+					"math::asin" => n.asin(),
+					"math::acos" => n.acos(),
+					"math::atan" => n.atan(),
+					"math::ln" | "math::log2" | "math::log10" if n <= 0.0 => {
+						return Err(domain(
+							range,
+							&format!("cannot take the logarithm of {n}, it must be positive"),
+						))
+					}
+					"math::ln" => n.ln(),
+					"math::log2" => n.log2(),
+					"math::log10" => n.log10(),
+					_ => panic!("not a math function"),
+				})
+			}
+		})
+	}
+}
+
+mod fs {
+	use super::{signature, strs};
+	use crate::{
+		data::{Data, DataType},
+		error::Error,
+	};
+	use std::{fs, io, ops::Range, path::Path};
+
+	fn failed(range: &Range<usize>, action: &str, path: &str, error: io::Error) -> Error {
+		Error::new(
+			"0014",
+			&format!("could not {action} `{path}`"),
+			&error.to_string(),
+			range.clone(),
+		)
+	}
+
+	/// Calls the `fs::` function `x`, failures are reported as errors with
+	/// the reason given by the operating system.
+	pub fn call(x: &str, range: &Range<usize>, params: Vec<Data>) -> Result<Option<Data>, Error> {
+		use DataType::Str;
+
+		Ok(match x {
+			"fs::read_file" | "fs::read_lines" => match signature(x, range, params, [Str])? {
+				[Data::Str(path)] => {
+					let contents = fs::read_to_string(&path).map_err(|e| failed(range, "read", &path, e))?;
+
+					Some(if x == "fs::read_file" {
+						Data::Str(contents)
+					} else {
+						strs(contents.lines().map(str::to_string))
+					})
+				}
+				_ => unreachable!(),
+			},
+			"fs::write_file" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(path), Data::Str(contents)] => {
+					fs::write(&path, contents).map_err(|e| failed(range, "write", &path, e))?;
+					None
+				}
+				_ => unreachable!(),
+			},
+			"fs::append_file" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(path), Data::Str(contents)] => {
+					use io::Write;
+
+					fs::OpenOptions::new()
+						.create(true)
+						.append(true)
+						.open(&path)
+						.and_then(|mut file| file.write_all(contents.as_bytes()))
+						.map_err(|e| failed(range, "append to", &path, e))?;
+					None
+				}
+				_ => unreachable!(),
+			},
+			"fs::exists" => match signature(x, range, params, [Str])? {
+				[Data::Str(path)] => Some(Data::Bool(Path::new(&path).exists())),
+				_ => unreachable!(),
+			},
+			"fs::list_dir" => match signature(x, range, params, [Str])? {
+				[Data::Str(path)] => {
+					let mut names = fs::read_dir(&path)
+						.and_then(|entries| {
+							entries
+								.map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+								.collect::<io::Result<Vec<_>>>()
+						})
+						.map_err(|e| failed(range, "list", &path, e))?;
+					names.sort();
+
+					Some(strs(names.into_iter()))
+				}
+				_ => unreachable!(),
+			},
+			"fs::mkdir" => match signature(x, range, params, [Str])? {
+				[Data::Str(path)] => {
+					fs::create_dir_all(&path).map_err(|e| failed(range, "create", &path, e))?;
+					None
+				}
+				_ => unreachable!(),
+			},
+			"fs::remove" => match signature(x, range, params, [Str])? {
+				[Data::Str(path)] => {
+					let removed = if Path::new(&path).is_dir() {
+						fs::remove_dir(&path)
+					} else {
+						fs::remove_file(&path)
+					};
+					removed.map_err(|e| failed(range, "remove", &path, e))?;
+					None
+				}
+				_ => unreachable!(),
+			},
+			"fs::join" => match signature(x, range, params, [Str, Str])? {
+				[Data::Str(base), Data::Str(path)] => Some(Data::Str(
+					Path::new(&base).join(path).to_string_lossy().into_owned(),
+				)),
+				_ => unreachable!(),
+			},
+			_ => panic!("not a filesystem function"),
+		})
+	}
+}
+
+mod process {
+	use super::signature;
+	use crate::{
+		data::{Data, DataType, Value},
+		error::Error,
+	};
+	use std::{
+		cell::RefCell,
+		io::{self, ErrorKind, Read, Write},
+		ops::Range,
+		process::{Command, ExitStatus, Stdio},
+		sync::mpsc::{self, Sender},
+		thread,
+	};
+
+	fn failed(range: &Range<usize>, cmd: &str, error: io::Error) -> Error {
+		Error::new(
+			"0014",
+			&format!("could not run `{cmd}`"),
+			&error.to_string(),
+			range.clone(),
+		)
+	}
+
+	/// Exit code of a process, `-1` if it was stopped by a signal.
+	fn code(status: ExitStatus) -> i32 {
+		status.code().unwrap_or(-1)
+	}
+
+	fn command(cmd: &str, args: Vec<String>) -> Command {
+		let mut command = Command::new(cmd);
+		command.args(args);
+		command
+	}
+
+	/// Converts a parameter whose type `signature` has already checked.
+	fn checked<T: Value>(data: Data) -> T {
+		T::from_data(data).unwrap_or_else(|_| unreachable!())
+	}
+
+	/// Sends what is read from `pipe` to `sender` as it arrives, along with
+	/// `index`, until it is closed.
+	fn forward(mut pipe: impl Read + Send + 'static, index: usize, sender: Sender<(usize, Vec<u8>)>) {
+		thread::spawn(move || {
+			let mut buffer = [0; 4096];
+			loop {
+				match pipe.read(&mut buffer) {
+					Ok(0) => break,
+					Ok(n) => {
+						if sender.send((index, buffer[..n].to_vec())).is_err() {
+							break;
+						}
+					}
+					Err(e) if e.kind() == ErrorKind::Interrupted => {}
+					Err(_) => break,
+				}
+			}
+		});
+	}
+
+	/// Runs `cmd` with `args` until it exits, writing its stdout and stderr
+	/// to `outs` as it prints them, or discarding them where `outs` is `None`.
+	fn spawn(
+		range: &Range<usize>,
+		cmd: &str,
+		args: Vec<String>,
+		outs: [Option<&RefCell<dyn Write>>; 2],
+	) -> Result<Data, Error> {
+		let pipe = |out: Option<_>| match out {
+			Some(_) => Stdio::piped(),
+			None => Stdio::null(),
+		};
+
+		let mut child = command(cmd, args)
+			.stdin(Stdio::null())
+			.stdout(pipe(outs[0]))
+			.stderr(pipe(outs[1]))
+			.spawn()
+			.map_err(|e| failed(range, cmd, e))?;
+
+		let (sender, receiver) = mpsc::channel();
+		if let Some(stdout) = child.stdout.take() {
+			forward(stdout, 0, sender.clone());
+		}
+		if let Some(stderr) = child.stderr.take() {
+			forward(stderr, 1, sender);
+		}
+
+		// the process is waited for even if writing its output fails, so
+		// that it does not keep running
+		let mut written = Ok(());
+		for (index, bytes) in receiver {
+			if let (Some(out), Ok(())) = (outs[index], &written) {
+				let mut out = out.borrow_mut();
+				written = out.write_all(&bytes).and_then(|()| out.flush());
+			}
+		}
+
+		let status = child.wait().map_err(|e| failed(range, cmd, e))?;
+		written.map_err(|e| {
+			Error::new(
+				"0014",
+				&format!("could not write the output of `{cmd}`"),
+				&e.to_string(),
+				range.clone(),
+			)
+		})?;
+
+		Ok(Data::Int(code(status)))
+	}
+
+	/// Calls the `process::` function `x`, output is captured as
+	/// `[stdout, stderr, status]` since Oxido has no tuples. What
+	/// `process::spawn` prints is written to `outs`.
+	pub fn call(
+		x: &str,
+		range: &Range<usize>,
+		params: Vec<Data>,
+		outs: [Option<&RefCell<dyn Write>>; 2],
+	) -> Result<Data, Error> {
+		use DataType::Str;
+		let args = DataType::Vector(Box::new(Str));
+
+		let (cmd, args, input) = match x {
+			"process::run_input" => {
+				let [cmd, args, input] = signature(x, range, params, [Str, args, Str])?;
+				(cmd, args, Some(checked::<String>(input)))
+			}
+			_ => {
+				let [cmd, args] = signature(x, range, params, [Str, args])?;
+				(cmd, args, None)
+			}
+		};
+		let (cmd, args) = (checked::<String>(cmd), checked::<Vec<String>>(args));
+
+		if x == "process::spawn" {
+			return spawn(range, &cmd, args, outs);
+		}
+
+		let mut child = command(&cmd, args)
+			.stdin(if input.is_some() {
+				Stdio::piped()
+			} else {
+				Stdio::null()
+			})
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.map_err(|e| failed(range, &cmd, e))?;
+
+		// written from another thread so a process which fills its stdout
+		// before reading all of stdin cannot block
+		let writer = match (input, child.stdin.take()) {
+			(Some(input), Some(mut stdin)) => {
+				Some(thread::spawn(move || stdin.write_all(input.as_bytes())))
+			}
+			_ => None,
+		};
+
+		let output = child
+			.wait_with_output()
+			.map_err(|e| failed(range, &cmd, e))?;
+
+		if let Some(writer) = writer {
+			match writer.join() {
+				// a process may exit without reading all of its input
+				Ok(Ok(())) | Ok(Err(_)) => {}
+				Err(_) => panic!("thread writing to stdin of `{cmd}` panicked"),
+			}
+		}
+
+		Ok(Data::Vector(
+			vec![
+				Data::Str(String::from_utf8_lossy(&output.stdout).into_owned()),
+				Data::Str(String::from_utf8_lossy(&output.stderr).into_owned()),
+				Data::Str(code(output.status).to_string()),
+			],
+			Str,
+		))
+	}
+}
+
+mod test {
+	use crate::{data::Data, error::Error};
+	use std::ops::Range;
+
+	pub fn assert(range: &Range<usize>, data: Data) -> Result<(), Error> {
+		match data {
+			Data::Bool(true) => Ok(()),
+			Data::Bool(false) => Err(Error::new(
+				"0013",
+				"assertion failed",
+				"this condition is false",
+				range.clone(),
+			)),
+			_ => Err(Error::new(
+				"0011",
 				"incorrect data type",
+				&format!("mismatched data types expected `bool` found {data}"),
+				range.clone(),
+			)),
+		}
+	}
+
+	pub fn assert_eq(range: &Range<usize>, params: Vec<Data>) -> Result<(), Error> {
+		let [left, right] = <[Data; 2]>::try_from(params).map_err(|params| {
+			Error::new(
+				"0004",
+				"wrong number of arguments were passed",
 				&format!(
-					"mismatched data types expected `int | bool | str` found {}",
-					data
+					"`assert_eq` expects 2 arguments but {} were passed",
+					params.len()
 				),
-				range,
-			),
+				range.clone(),
+			)
+		})?;
+
+		if left == right {
+			return Ok(());
 		}
+
+		Err(Error::new(
+			"0013",
+			"assertion failed",
+			&format!(
+				"left is `{}` : {} but right is `{}` : {}",
+				left.as_string(),
+				left.r#type(),
+				right.as_string(),
+				right.r#type()
+			),
+			range.clone(),
+		))
 	}
 }
 
 mod io {
-	use crate::data::Data;
-	use std::io::{stdin, stdout, Write};
+	use crate::{data::Data, error::Error};
+	use std::{
+		cell::RefCell,
+		io::{self, ErrorKind, Read, Write},
+		ops::Range,
+	};
 
 	#[cfg(target_arch = "wasm32")]
 	use wasm_bindgen::prelude::wasm_bindgen;
 
-	pub fn read() -> Data {
-		let mut s = String::new();
-		stdin().read_line(&mut s).unwrap();
-		if let Some('\n') = s.chars().next_back() {
-			s.pop();
-		}
-		if let Some('\r') = s.chars().next_back() {
-			s.pop();
-		}
-		Data::Str(s)
+	fn failed(range: &Range<usize>, action: &str, error: io::Error) -> Error {
+		Error::new(
+			"0014",
+			&format!("could not {action}"),
+			&error.to_string(),
+			range.clone(),
+		)
 	}
 
-	#[cfg(target_arch = "wasm32")]
-	pub fn p(data: &str) {
-		#[wasm_bindgen]
-		extern "C" {
-			#[wasm_bindgen(js_namespace = console)]
-			fn log(s: &str);
-		}
-		macro_rules! console_log {
-            ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
-        }
-		console_log!("{data}");
-	}
-
-	#[cfg(not(target_arch = "wasm32"))]
-	pub fn p(data: &str) {
-		print!("{data}");
-	}
-
-	pub fn print(datas: Vec<Data>) {
-		for data in datas {
-			match data {
-				Data::Int(i) => p(&i.to_string()),
-				Data::Bool(b) => p(&b.to_string()),
-				Data::Str(s) => p(&s),
-				Data::Vector(vec, _) => {
-					p("[");
-					for (i, v) in vec.iter().enumerate() {
-						print(vec![v.clone()]);
-						if i != vec.len() - 1 {
-							p(", ");
-						}
-					}
-					p("]")
-				}
+	/// Reads a line from `stdin` without its line ending, a byte at a time so
+	/// that nothing after the line is taken from anyone else reading it.
+	pub fn read(range: &Range<usize>, stdin: &RefCell<dyn Read>) -> Result<Data, Error> {
+		let mut stdin = stdin.borrow_mut();
+		let mut line = vec![];
+		let mut byte = [0];
+
+		loop {
+			match stdin.read(&mut byte) {
+				Ok(0) => break,
+				Ok(_) if byte[0] == b'\n' => break,
+				Ok(_) => line.push(byte[0]),
+				Err(e) if e.kind() == ErrorKind::Interrupted => {}
+				Err(e) => return Err(failed(range, "read from stdin", e)),
 			}
 		}
+		if line.last() == Some(&b'\r') {
+			line.pop();
+		}
 
-		stdout().flush().unwrap();
+		Ok(Data::Str(String::from_utf8_lossy(&line).into_owned()))
 	}
 
-	pub fn println(datas: Vec<Data>) {
+	/// Writes each of `datas` followed by `end` to `out`, or only `end` if
+	/// there are none.
+	pub fn write(
+		range: &Range<usize>,
+		name: &str,
+		out: &RefCell<dyn Write>,
+		datas: Vec<Data>,
+		end: &str,
+	) -> Result<(), Error> {
+		let mut text = datas
+			.iter()
+			.map(|data| data.as_string() + end)
+			.collect::<String>();
 		if datas.is_empty() {
-			p("\n");
-			return;
-		}
-		for data in datas {
-			match data {
-				Data::Int(i) => p(&format!("{i}\n")),
-				Data::Bool(b) => p(&format!("{b}\n")),
-				Data::Str(s) => p(&format!("{s}\n")),
-				Data::Vector(vec, _) => {
-					p("[");
-					for (i, v) in vec.iter().enumerate() {
-						print(vec![v.clone()]);
-						if i != vec.len() - 1 {
-							p(", ");
-						}
-					}
-					p("]")
-				}
+			text += end;
+		}
+
+		let mut out = out.borrow_mut();
+		out
+			.write_all(text.as_bytes())
+			.and_then(|()| out.flush())
+			.map_err(|e| failed(range, &format!("write to {name}"), e))
+	}
+
+	/// Writes to the browser console, as there is no stdout.
+	#[cfg(target_arch = "wasm32")]
+	pub struct Console;
+
+	#[cfg(target_arch = "wasm32")]
+	impl Write for Console {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			#[wasm_bindgen]
+			extern "C" {
+				#[wasm_bindgen(js_namespace = console)]
+				fn log(s: &str);
 			}
+
+			log(&String::from_utf8_lossy(buf));
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
 		}
 	}
 }
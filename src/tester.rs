@@ -0,0 +1,142 @@
+use std::{cell::RefCell, io, rc::Rc, time::Instant};
+
+use crate::{
+	ast::AstNode,
+	error::{self, Error},
+	interpreter::Interpreter,
+	lexer::Lexer,
+	parser::Parser,
+};
+
+/// Runs every `test fn` declared at the top level of `contents` with the
+/// functions and variables declared beside it, returns whether all of them
+/// passed.
+pub fn run(name: &str, contents: &str) -> bool {
+	let tokens = match Lexer::new(contents).run() {
+		Ok(tokens) => tokens.to_vec(),
+		Err(e) => {
+			error::report(name, contents, &[e]);
+			return false;
+		}
+	};
+
+	let (ast, errors) = Parser::new().run(tokens);
+	if !errors.is_empty() {
+		error::report(name, contents, &errors);
+		return false;
+	}
+
+	let (tests, declarations): (Vec<_>, Vec<_>) = ast
+		.into_iter()
+		.filter(|(node, _)| {
+			matches!(
+				node,
+				AstNode::Test(..) | AstNode::FunctionDeclaration(..) | AstNode::Assignment(..)
+			)
+		})
+		.partition(|(node, _)| matches!(node, AstNode::Test(..)));
+
+	// declarations are set up the way tests run, and what they print is only
+	// shown if one of them fails
+	let output = Rc::new(RefCell::new(vec![]));
+	let mut interpreter = Interpreter::new();
+	interpreter.set_stdin(Rc::new(RefCell::new(io::empty())));
+	interpreter.set_stdout(output.clone());
+	interpreter.set_stderr(output.clone());
+
+	if let Err(e) = interpreter.run(declarations) {
+		print!("{}", String::from_utf8_lossy(&output.take()));
+		error::report(name, contents, &[e]);
+		return false;
+	}
+
+	let total = tests.len();
+	println!("\nrunning {total} tests in {name}");
+
+	let start = Instant::now();
+	let mut failures: Vec<(String, Vec<u8>, Error)> = vec![];
+
+	for (node, range) in tests {
+		let AstNode::Test(test, body) = node else {
+			continue;
+		};
+
+		// output is only shown for failing tests, and tests reading input get
+		// none instead of waiting for it
+		let output = Rc::new(RefCell::new(vec![]));
+		let mut run = interpreter.clone();
+		run.set_stdout(output.clone());
+		run.set_stderr(output.clone());
+
+		let result = run.run(body).and_then(|()| match run.exit_code() {
+			0 => Ok(()),
+			code => Err(Error::new(
+				"0013",
+				"test exited with a failure",
+				&format!("the test ended with status {code}"),
+				range,
+			)),
+		});
+
+		match result {
+			Ok(()) => println!("test {test} ... \x1b[32mok\x1b[0m"),
+			Err(e) => {
+				println!("test {test} ... \x1b[31mFAILED\x1b[0m");
+				failures.push((test, output.take(), e));
+			}
+		}
+	}
+
+	if !failures.is_empty() {
+		println!("\nfailures:");
+		for (test, output, e) in failures.iter() {
+			println!("\n---- {test} ----");
+			print!("{}", String::from_utf8_lossy(output));
+			error::report(name, contents, std::slice::from_ref(e));
+		}
+	}
+
+	let result = if failures.is_empty() {
+		"\x1b[32mok\x1b[0m"
+	} else {
+		"\x1b[31mFAILED\x1b[0m"
+	};
+	println!(
+		"\ntest result: {result}. {} passed; {} failed; finished in {:?}",
+		total - failures.len(),
+		failures.len(),
+		start.elapsed()
+	);
+
+	failures.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::run;
+
+	#[test]
+	fn globals() {
+		let file = "
+			let factor: int = 2;
+			let names: vec<str> = [\"a\", \"b\"];
+			fn double(x: int) -> int { return x * factor; }
+
+			test fn reads_globals() {
+				assert_eq(double(factor), 4);
+				assert_eq(vector::len(names), 2);
+			}
+		";
+		assert!(run("globals.oxi", file));
+	}
+
+	#[test]
+	fn tests_do_not_share_globals() {
+		let file = "
+			let count: int = 0;
+			test fn first() { count = count + 1; assert_eq(count, 1); }
+			test fn second() { count = count + 1; assert_eq(count, 1); }
+		";
+		assert!(run("globals.oxi", file));
+	}
+}
@@ -1,10 +1,29 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range};
 
-use crate::data::DataType;
+use crate::data::{self, DataType};
 
-pub type Tokens = Vec<(Token, usize)>;
+pub type Tokens = Vec<(Token, Span)>;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Location of a token, `range` is in bytes while `line` and `column` count
+/// characters starting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+	pub range: Range<usize>,
+	pub line: usize,
+	pub column: usize,
+}
+
+impl Span {
+	pub fn new(range: Range<usize>, line: usize, column: usize) -> Self {
+		Self {
+			range,
+			line,
+			column,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
 	Let,
 	If,
@@ -32,10 +51,12 @@ pub enum Token {
 
 	Str(String),
 	Int(i32),
+	Float(f64),
 	Bool(bool),
 
 	Semicolon,
 	Comma,
+	Range,
 	LParen,
 	RParen,
 	LCurly,
@@ -77,10 +98,12 @@ impl Token {
 
 			Token::Str(string) => string.to_string(),
 			Token::Int(i) => format!("{i}"),
+			Token::Float(f) => data::float(*f),
 			Token::Bool(b) => format!("{b}"),
 
 			Token::Semicolon => String::from(";"),
 			Token::Comma => String::from(","),
+			Token::Range => String::from(".."),
 			Token::LParen => String::from("("),
 			Token::RParen => String::from(")"),
 			Token::LCurly => String::from("{"),
@@ -93,8 +116,4 @@ impl Token {
 			Token::Return => String::from("return"),
 		}
 	}
-
-	pub fn len(&self) -> usize {
-		self.as_string().len()
-	}
 }
@@ -0,0 +1,45 @@
+//! Runs `oxido debug` with commands and the program's input both on stdin,
+//! as they are when the debugger is used from a terminal.
+
+use std::{
+	io::Write,
+	path::Path,
+	process::{Command, Stdio},
+	thread,
+	time::{Duration, Instant},
+};
+
+#[test]
+fn debugged_programs_read_stdin() {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_oxido"))
+		.args(["debug", "examples/echo.oxi"])
+		.current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+		.env("NO_COLOR", "1")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(b"c\nhello\n")
+		.unwrap();
+
+	let start = Instant::now();
+	while child.try_wait().unwrap().is_none() {
+		if start.elapsed() > Duration::from_secs(10) {
+			child.kill().unwrap();
+			panic!("the debugger did not finish, it may be waiting for stdin");
+		}
+		thread::sleep(Duration::from_millis(10));
+	}
+
+	let output = child.wait_with_output().unwrap();
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(output.status.success(), "{stdout}");
+	assert!(stdout.contains("hello\n"), "{stdout}");
+	assert!(stdout.contains("program finished"), "{stdout}");
+}
@@ -0,0 +1,130 @@
+//! Runs every `.oxi` file under `tests/golden` and compares its stdout,
+//! stderr and exit code with the `.stdout`, `.stderr` and `.status` files
+//! beside it. Input is read from a `.stdin` file if there is one.
+//!
+//! The files in `examples` are run as they are, their expectations are kept
+//! in `tests/golden/examples`.
+//!
+//! Run with `BLESS=1` to record the current output as the expectation.
+
+use std::{
+	env, fs,
+	io::Write,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+};
+
+/// Cases which need a Unix shell and its tools.
+const UNIX_ONLY: [&str; 1] = ["stdlib/process.oxi"];
+
+/// A file to run from `dir`, and the path its expectations are kept at
+/// without their extension.
+struct Case {
+	dir: PathBuf,
+	file: PathBuf,
+	expected: PathBuf,
+}
+
+fn cases(dir: &Path, cases: &mut Vec<PathBuf>) {
+	for entry in fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+
+		if path.is_dir() {
+			self::cases(&path, cases);
+		} else if path.extension().is_some_and(|ext| ext == "oxi") {
+			cases.push(path);
+		}
+	}
+}
+
+/// Runs `case` and returns its stdout, stderr and exit code.
+fn run(case: &Case) -> [String; 3] {
+	let stdin = fs::read(case.expected.with_extension("stdin")).unwrap_or_default();
+
+	let mut child = Command::new(env!("CARGO_BIN_EXE_oxido"))
+		.arg(&case.file)
+		.current_dir(&case.dir)
+		.env("NO_COLOR", "1")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+
+	child.stdin.take().unwrap().write_all(&stdin).unwrap();
+	let output = child.wait_with_output().unwrap();
+
+	let status = match output.status.code() {
+		Some(code) => format!("{code}\n"),
+		None => String::from("killed\n"),
+	};
+
+	[
+		String::from_utf8_lossy(&output.stdout).into_owned(),
+		String::from_utf8_lossy(&output.stderr).into_owned(),
+		status,
+	]
+}
+
+#[test]
+fn golden() {
+	let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+	let root = manifest.join("tests/golden");
+	let bless = env::var_os("BLESS").is_some();
+
+	let mut files = vec![];
+	self::cases(&root, &mut files);
+	let mut cases = files
+		.into_iter()
+		.map(|path| Case {
+			dir: root.clone(),
+			file: path.strip_prefix(&root).unwrap().to_path_buf(),
+			expected: path,
+		})
+		.collect::<Vec<_>>();
+
+	let mut examples = vec![];
+	self::cases(&manifest.join("examples"), &mut examples);
+	cases.extend(examples.into_iter().map(|path| {
+		let file = path.strip_prefix(manifest).unwrap().to_path_buf();
+		Case {
+			dir: manifest.to_path_buf(),
+			expected: root.join(&file),
+			file,
+		}
+	}));
+
+	cases.retain(|case| cfg!(unix) || !UNIX_ONLY.iter().any(|file| case.file == Path::new(file)));
+	cases.sort_by(|a, b| a.expected.cmp(&b.expected));
+
+	let mut failures = vec![];
+
+	for case in &cases {
+		let actual = run(case);
+
+		for (extension, actual) in ["stdout", "stderr", "status"].iter().zip(actual) {
+			let path = case.expected.with_extension(extension);
+
+			if bless {
+				fs::write(&path, actual).unwrap();
+				continue;
+			}
+
+			let expected = fs::read_to_string(&path).unwrap_or_default();
+			if expected != actual {
+				failures.push(format!(
+					"{}\n--- expected\n{expected}\n+++ actual\n{actual}",
+					path.strip_prefix(&root).unwrap().display()
+				));
+			}
+		}
+	}
+
+	assert!(!cases.is_empty(), "no cases found in {}", root.display());
+	assert!(
+		failures.is_empty(),
+		"{} expectations differ, rerun with `BLESS=1` to update them\n\n{}",
+		failures.len(),
+		failures.join("\n")
+	);
+}